@@ -0,0 +1,1637 @@
+pub mod scope;
+
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use scope::{FunctionSig, Scope, StructDef, Symbol};
+
+use crate::{diagnostics::{codes, Diagnostic, Diagnostics}, lexer::{self, Span, Token}, parser::{ast::{Expr, ExprKind, IncludedModule, Stmt, StmtKind, Type, TypeKind}, Parser}, ty::Ty};
+
+/// What checking an included file produced, everything a later include of the same file needs
+#[derive(Clone)]
+struct CheckedModule {
+    ast: Stmt,
+    exports: Vec<(String, Symbol)>,
+    structs: HashMap<String, StructDef>,
+}
+
+/// Resolves names and checks types before any IR is emitted, filling in the
+/// type of every expression and type annotation so codegen never has to guess
+pub struct Checker<'a> {
+    scope: Scope,
+    structs: HashMap<String, StructDef>,
+    return_type: Option<Ty>,
+
+    /// Struct whose methods are being checked, what `this` refers to
+    this_type: Option<String>,
+
+    /// Labels of the loops enclosing the statement being checked, innermost last
+    loops: Vec<Option<String>>,
+
+    include_paths: Vec<String>,
+    cwd: String,
+
+    /// Files that are being checked, this one last, so an include that leads back to one is caught
+    including: Vec<PathBuf>,
+    /// Files already checked anywhere in the program, so one included from several places is only checked and compiled once
+    modules: HashMap<PathBuf, CheckedModule>,
+
+    diagnostics: &'a mut Diagnostics,
+}
+
+impl<'a> Checker<'a> {
+    fn new(include_paths: Vec<String>, cwd: Option<String>, diagnostics: &'a mut Diagnostics) -> Self {
+        let _cwd = if let Some(c) = cwd {
+            c
+        } else {
+            std::env::current_dir().unwrap().to_string_lossy().to_string()
+        };
+
+        Self {
+            scope: Scope::new(),
+            structs: HashMap::new(),
+            return_type: None,
+
+            this_type: None,
+
+            loops: vec![],
+
+            include_paths,
+            cwd: _cwd,
+
+            including: vec![],
+            modules: HashMap::new(),
+
+            diagnostics,
+        }
+    }
+
+    pub fn check(ast: &mut Stmt, path: &str, include_paths: Vec<String>, cwd: Option<String>, diagnostics: &'a mut Diagnostics) -> Self {
+        Self::check_module(ast, vec![canonical_path(path)], HashMap::new(), include_paths, cwd, diagnostics)
+    }
+
+    fn check_module(ast: &mut Stmt, including: Vec<PathBuf>, modules: HashMap<PathBuf, CheckedModule>, include_paths: Vec<String>, cwd: Option<String>, diagnostics: &'a mut Diagnostics) -> Self {
+        let mut checker = Self::new(include_paths, cwd, diagnostics);
+        checker.including = including;
+        checker.modules = modules;
+        checker.declare_items(ast);
+        checker.check_stmt(ast);
+        checker
+    }
+
+    fn error(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.report(diagnostic);
+    }
+
+    fn mismatch(&mut self, expected: &Ty, found: &Ty, span: Span) {
+        self.error(Diagnostic::error(codes::MISMATCHED_TYPES, "mismatched types")
+            .with_label(span, format!("expected `{}`, found `{}`", expected, found)));
+    }
+
+    fn resolve_type(&mut self, _type: &mut Type) -> Ty {
+        let ty = match &mut _type.kind {
+            TypeKind::Symbol(name) => {
+                if let Some(ty) = Ty::from_name(name) {
+                    ty
+                } else if name == "this" {
+                    match &self.this_type {
+                        Some(this) => Ty::Struct(this.clone()),
+                        None => {
+                            self.error(Diagnostic::error(codes::INVALID_RECEIVER, "`this` can only be used in struct methods")
+                                .with_label(_type.span, "not inside a struct"));
+                            Ty::Error
+                        },
+                    }
+                } else if let Some(Symbol::Struct(_)) = self.scope.resolve(name) {
+                    Ty::Struct(name.clone())
+                } else {
+                    let name = name.clone();
+                    self.error(Diagnostic::error(codes::UNRESOLVED_TYPE, format!("cannot find type `{}`", name))
+                        .with_label(_type.span, "not found in this scope"));
+                    Ty::Error
+                }
+            },
+            TypeKind::Ref(inner) => Ty::Ref(Box::new(self.resolve_type(inner))),
+            TypeKind::Array { len, inner } => match self.resolve_type(inner) {
+                Ty::Error => Ty::Error,
+                elem => Ty::Array { len: *len, elem: Box::new(elem) },
+            },
+            TypeKind::Slice { inner, mutable } => match self.resolve_type(inner) {
+                Ty::Error => Ty::Error,
+                elem => Ty::Slice { elem: Box::new(elem), mutable: *mutable },
+            },
+        };
+
+        _type.ty = Some(ty.clone());
+        ty
+    }
+
+    fn check_stmt(&mut self, stmt: &mut Stmt) {
+        let span = stmt.span;
+        match &mut stmt.kind {
+            StmtKind::Block(body) => {
+                self.scope.push();
+                for s in body.iter_mut() {
+                    self.check_stmt(s);
+                }
+                self.scope.pop();
+            },
+            StmtKind::Expression(expr) => {
+                self.check_expr(expr, None);
+            },
+            StmtKind::VariableDeclaration { name, mutable, explicit_type, value } => {
+                let ty = if let Some(explicit_type) = explicit_type {
+                    let expected = self.resolve_type(explicit_type);
+                    let found = self.check_coerced(value, &expected);
+                    if !found.compatible(&expected) {
+                        self.error(Diagnostic::error(codes::MISMATCHED_TYPES, "mismatched types")
+                            .with_label(value.span, format!("expected `{}`, found `{}`", expected, found))
+                            .with_secondary(explicit_type.span, "expected due to this"));
+                    }
+                    expected
+                } else {
+                    self.check_value(value, None)
+                };
+
+                // Shadowing is fine from an inner block, but not within the same one
+                if let Some(previous) = self.scope.resolve_local(name) {
+                    let previous = previous.span();
+                    self.error(Diagnostic::error(codes::REDEFINITION, format!("`{}` is already defined in this block", name))
+                        .with_label(span, "redefined here")
+                        .with_secondary(previous, "previous definition here")
+                        .with_note("a variable can only be shadowed from an inner block"));
+                }
+                self.scope.define(name.clone(), Symbol::Variable { ty, mutable: *mutable, span });
+            },
+            StmtKind::If { condition, body, _else } => {
+                self.check_condition(condition);
+                self.check_stmt(body);
+                if let Some(_else) = _else {
+                    self.check_stmt(_else);
+                }
+            },
+            StmtKind::While { condition, body, label } => {
+                self.check_condition(condition);
+                self.loops.push(label.clone());
+                self.check_stmt(body);
+                self.loops.pop();
+            },
+            StmtKind::For { variable, iterable, body, label } => {
+                let ty = self.check_iterable(iterable);
+
+                self.scope.push();
+                self.scope.define(variable.clone(), Symbol::Variable { ty, mutable: false, span });
+
+                self.loops.push(label.clone());
+                self.check_stmt(body);
+                self.loops.pop();
+                self.scope.pop();
+            },
+            StmtKind::Break { label } => self.check_loop_control("break", label, span),
+            StmtKind::Continue { label } => self.check_loop_control("continue", label, span),
+            StmtKind::Return { value } => {
+                let expected = self.return_type.clone();
+                let found = match (value.as_deref_mut(), &expected) {
+                    (Some(value), Some(expected)) if !expected.is_void() => self.check_coerced(value, expected),
+                    (Some(value), _) => self.check_expr(value, None),
+                    (None, _) => Ty::Void,
+                };
+                let at = value.as_ref().map_or(span, |value| value.span);
+                match expected {
+                    Some(expected) => if !found.compatible(&expected) {
+                        self.mismatch(&expected, &found, at);
+                    },
+                    None => self.error(Diagnostic::error(codes::INVALID_OPERATION, "`return` outside of a function")
+                        .with_label(span, "cannot return from here")),
+                }
+            },
+            StmtKind::Delete { value } => {
+                let ty = self.check_value(value, None);
+                if !matches!(ty, Ty::Ref(_) | Ty::Error) {
+                    self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot delete `{}`", ty))
+                        .with_label(value.span, "expected a reference created with `new`"));
+                }
+            },
+            StmtKind::StructDeclaration { .. } | StmtKind::FunctionDeclaration { .. } | StmtKind::Extern { .. } | StmtKind::Include { .. }
+                if self.return_type.is_some() => {
+                self.error(Diagnostic::error(codes::UNSUPPORTED, "declarations are only allowed at the top level")
+                    .with_label(span, "move this out of the function"));
+            },
+            StmtKind::StructDeclaration { .. } => self.check_struct_declaration(stmt),
+            StmtKind::FunctionDeclaration { .. } => self.check_function(stmt),
+            // Declared up front by `declare_items`
+            StmtKind::Extern { .. } | StmtKind::Include { .. } => {},
+            StmtKind::Link { .. } => {},
+            StmtKind::Field { .. } | StmtKind::StructInitField { .. } => unreachable!("the parser only builds fields inside declarations and struct literals"),
+        }
+    }
+
+    fn define_global(&mut self, name: String, symbol: Symbol) {
+        if let Some(previous) = self.scope.resolve_local(&name) {
+            let previous = previous.span();
+            self.error(Diagnostic::error(codes::REDEFINITION, format!("`{}` is defined multiple times", name))
+                .with_label(symbol.span(), "redefined here")
+                .with_secondary(previous, "previous definition here"));
+        }
+        self.scope.define(name, symbol);
+    }
+
+    fn resolve_fields(&mut self, fields: &mut [Stmt]) -> Vec<Ty> {
+        let mut types = vec![];
+        for field in fields.iter_mut() {
+            let StmtKind::Field { _type, .. } = &mut field.kind else {
+                panic!("Expected field");
+            };
+            types.push(self.resolve_type(_type));
+        }
+        types
+    }
+
+    /// Registers every top level declaration before any body is checked, so
+    /// functions and types can be used ahead of where they are written
+    fn declare_items(&mut self, ast: &mut Stmt) {
+        let StmtKind::Block(body) = &mut ast.kind else {
+            panic!("Expected block");
+        };
+
+        for stmt in body.iter_mut() {
+            let span = stmt.span;
+            if let StmtKind::Include { path, module } = &mut stmt.kind {
+                let path = path.clone();
+                *module = self.check_include(&path, span);
+            }
+        }
+
+        // Names first so struct fields and signatures can refer to any struct
+        for stmt in body.iter() {
+            if let StmtKind::StructDeclaration { name, .. } = &stmt.kind {
+                self.define_global(name.clone(), Symbol::Struct(stmt.span));
+            }
+        }
+
+        for stmt in body.iter_mut() {
+            let span = stmt.span;
+            match &mut stmt.kind {
+                StmtKind::StructDeclaration { .. } => self.declare_struct(stmt),
+                StmtKind::FunctionDeclaration { name, .. } => {
+                    let name = name.clone();
+                    self.declare_function(stmt, name);
+                },
+                StmtKind::Extern { name, return_type, arguments, .. } => {
+                    let params = self.resolve_fields(arguments);
+                    let ret = self.resolve_type(return_type);
+                    let name = name.clone();
+                    self.define_global(name, Symbol::Function(FunctionSig { params, ret, span }));
+                },
+                _ => {},
+            }
+        }
+
+        for stmt in body.iter() {
+            if let StmtKind::StructDeclaration { name, .. } = &stmt.kind
+                && self.contains_by_value(&Ty::Struct(name.clone()), name, &mut vec![]) {
+                self.error(Diagnostic::error(codes::RECURSIVE_TYPE, format!("recursive struct `{}` has infinite size", name))
+                    .with_label(stmt.span, "contains itself by value")
+                    .with_note(format!("use a reference such as `&{}` to break the cycle", name)));
+            }
+        }
+    }
+
+    /// Whether `ty` holds a `target` inside it without going through a reference
+    fn contains_by_value(&self, ty: &Ty, target: &str, seen: &mut Vec<String>) -> bool {
+        let Ty::Struct(name) = ty else {
+            return false;
+        };
+
+        let Some(def) = self.structs.get(name) else {
+            return false;
+        };
+
+        for (_, field) in def.fields.iter() {
+            // An array stores its elements inline, so it contains whatever they contain
+            let mut field = field;
+            while let Ty::Array { elem, .. } = field {
+                field = elem;
+            }
+
+            if let Ty::Struct(inner) = field {
+                if inner == target {
+                    return true;
+                }
+
+                if !seen.contains(inner) {
+                    seen.push(inner.clone());
+                    if self.contains_by_value(field, target, seen) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn declare_struct(&mut self, stmt: &mut Stmt) {
+        let span = stmt.span;
+        let StmtKind::StructDeclaration { name, fields, functions } = &mut stmt.kind else {
+            panic!("Expected struct declaration");
+        };
+
+        let name = name.clone();
+        let types = self.resolve_fields(fields);
+        let mut def = StructDef { fields: vec![], span };
+        for (field, ty) in fields.iter().zip(types) {
+            let StmtKind::Field { name: field_name, .. } = &field.kind else {
+                panic!("Expected field");
+            };
+
+            if def.field(field_name).is_some() {
+                self.error(Diagnostic::error(codes::REDEFINITION, format!("field `{}` is already declared", field_name))
+                    .with_label(field.span, "declared again here"));
+            }
+            def.fields.push((field_name.clone(), ty));
+        }
+
+        self.structs.insert(name.clone(), def);
+
+        let outer_this = self.this_type.replace(name.clone());
+        for function in functions.iter_mut() {
+            let StmtKind::FunctionDeclaration { name: method, arguments, .. } = &function.kind else {
+                panic!("Expected function declaration");
+            };
+
+            let has_receiver = arguments.first().is_some_and(|a| matches!(&a.kind, StmtKind::Field { name, .. } if name == "this"));
+            if !has_receiver {
+                self.error(Diagnostic::error(codes::INVALID_RECEIVER, format!("method `{}` must take `this` or `&this` as its first argument", method))
+                    .with_label(function.span, "missing receiver"));
+            }
+
+            let symbol = method_symbol(&name, method);
+            self.declare_function(function, symbol);
+        }
+        self.this_type = outer_this;
+    }
+
+    /// Resolves the signature of a function or method and defines it as `symbol`
+    fn declare_function(&mut self, stmt: &mut Stmt, symbol: String) {
+        let span = stmt.span;
+        let StmtKind::FunctionDeclaration { return_type, arguments, .. } = &mut stmt.kind else {
+            panic!("Expected function declaration");
+        };
+
+        let params = self.resolve_fields(arguments);
+        let ret = self.resolve_type(return_type);
+        self.define_global(symbol, Symbol::Function(FunctionSig { params, ret, span }));
+    }
+
+    fn check_struct_declaration(&mut self, stmt: &mut Stmt) {
+        let StmtKind::StructDeclaration { name, functions, .. } = &mut stmt.kind else {
+            panic!("Expected struct declaration");
+        };
+
+        let outer_this = self.this_type.replace(name.clone());
+        for function in functions.iter_mut() {
+            self.check_function(function);
+        }
+        self.this_type = outer_this;
+    }
+
+    /// Checks the body of a function or method declared by `declare_items`
+    fn check_function(&mut self, stmt: &mut Stmt) {
+        let StmtKind::FunctionDeclaration { name, return_type, arguments, body } = &mut stmt.kind else {
+            panic!("Expected function declaration");
+        };
+
+        self.scope.push();
+        for argument in arguments.iter() {
+            let StmtKind::Field { name, _type } = &argument.kind else {
+                panic!("Expected field");
+            };
+            self.scope.define(name.clone(), Symbol::Variable { ty: _type.ty().clone(), mutable: false, span: argument.span });
+        }
+
+        let ret = return_type.ty().clone();
+        let outer_return = self.return_type.replace(ret.clone());
+        let outer_loops = std::mem::take(&mut self.loops);
+        self.check_stmt(body);
+        self.loops = outer_loops;
+        self.return_type = outer_return;
+        self.scope.pop();
+
+        if !ret.is_void() && !ret.is_error() && !always_returns(body) {
+            self.error(Diagnostic::error(codes::MISSING_RETURN, format!("function `{}` may finish without returning a value", name))
+                .with_label(return_type.span, format!("expected `{}` because of this return type", ret)));
+        }
+    }
+
+    /// Checks what a `for` loops over, returning the type of the loop variable
+    fn check_iterable(&mut self, iterable: &mut Expr) -> Ty {
+        let ExprKind::Range { start, end, step, .. } = &mut iterable.kind else {
+            // Arrays and slices give their elements one at a time
+            let ty = self.check_value(iterable, None);
+            return match ty.element() {
+                Some(elem) => elem.clone(),
+                None => {
+                    if !ty.is_error() {
+                        self.error(Diagnostic::error(codes::UNSUPPORTED, format!("cannot iterate over `{}`", ty))
+                            .with_label(iterable.span, "not iterable")
+                            .with_note("ranges such as `[0..n]`, arrays and slices can be iterated over"));
+                    }
+                    Ty::Error
+                },
+            };
+        };
+
+        // `[0..n]` counts in the type of `n`
+        let hint = if is_untyped(start) && !is_untyped(end) { Some(self.check_value(end, None)) } else { None };
+        let ty = self.check_value(start, hint.as_ref());
+        if !ty.is_int() && !ty.is_error() {
+            self.error(Diagnostic::error(codes::MISMATCHED_TYPES, "range bounds must be integers")
+                .with_label(start.span, format!("this is `{}`", ty)));
+        }
+        let ty = if ty.is_int() { ty } else { Ty::Error };
+
+        let found = match hint {
+            Some(found) => found,
+            None => self.check_coerced(end, &ty),
+        };
+        if !found.compatible(&ty) {
+            self.mismatch(&ty, &found, end.span);
+        }
+
+        if let Some(step) = step {
+            // Other steps are checked when the loop starts
+            if let ExprKind::Int(value, _) = step.kind && value <= 0 {
+                self.error(Diagnostic::error(codes::INVALID_OPERATION, "range step must be positive")
+                    .with_label(step.span, format!("a step of {} never reaches the end", value)));
+            }
+
+            let found = self.check_coerced(step, &ty);
+            if !found.compatible(&ty) {
+                self.mismatch(&ty, &found, step.span);
+            }
+        }
+
+        // A range isn't a value of its own, it takes the type of what it produces
+        iterable.ty = Some(ty.clone());
+        ty
+    }
+
+    fn check_loop_control(&mut self, keyword: &str, label: &Option<String>, span: Span) {
+        if self.loops.is_empty() {
+            self.error(Diagnostic::error(codes::OUTSIDE_LOOP, format!("`{}` outside of a loop", keyword))
+                .with_label(span, format!("cannot `{}` outside of a loop", keyword)));
+        } else if let Some(label) = label
+            && !self.loops.iter().any(|l| l.as_ref() == Some(label)) {
+            self.error(Diagnostic::error(codes::UNRESOLVED_LABEL, format!("use of undeclared label `{}`", label))
+                .with_label(span, "no enclosing loop has this label"));
+        }
+    }
+
+    fn check_include(&mut self, path: &str, span: Span) -> Option<Box<IncludedModule>> {
+        let inc = match self.get_include_path(path, span) {
+            Ok(inc) => inc,
+            Err(e) => {
+                self.error(e);
+                return None;
+            },
+        };
+
+        let canonical = canonical_path(&inc);
+        if self.including.contains(&canonical) {
+            self.error(Diagnostic::error(codes::INCLUDE_CYCLE, format!("cyclic include of \"{}\"", inc))
+                .with_label(span, "included here while it is still being checked"));
+            return None;
+        }
+
+        if let Some(module) = self.modules.get(&canonical).cloned() {
+            self.import_module(&module);
+            return Some(Box::new(IncludedModule { path: inc, ast: module.ast, compile: false }));
+        }
+
+        let source = match std::fs::read_to_string(&inc) {
+            Ok(source) => source,
+            Err(e) => {
+                self.error(Diagnostic::error(codes::INCLUDE_NOT_FOUND, format!("failed to read included file \"{}\": {}", inc, e))
+                    .with_label(span, "included here"));
+                return None;
+            },
+        };
+
+        let file = self.diagnostics.add_file(inc.clone(), source.clone());
+        let tokens = lexer::tokenize(&source, file, self.diagnostics);
+        let mut ast = Parser::parse(tokens, self.diagnostics);
+
+        let cwd = Path::new(&inc).parent().unwrap().to_string_lossy().to_string();
+        let mut including = self.including.clone();
+        including.push(canonical.clone());
+        let modules = std::mem::take(&mut self.modules);
+        let checker = Checker::check_module(&mut ast, including, modules, self.include_paths.clone(), Some(cwd), self.diagnostics);
+
+        let module = CheckedModule {
+            ast: ast.clone(),
+            exports: checker.scope.exports().map(|(name, symbol)| (name.clone(), symbol.clone())).collect(),
+            structs: checker.structs,
+        };
+        self.modules = checker.modules;
+        self.import_module(&module);
+        self.modules.insert(canonical, module);
+
+        Some(Box::new(IncludedModule { path: inc, ast, compile: true }))
+    }
+
+    fn import_module(&mut self, module: &CheckedModule) {
+        for (name, symbol) in &module.exports {
+            self.scope.import(name.clone(), symbol.clone());
+        }
+        self.structs.extend(module.structs.clone());
+    }
+
+    fn get_include_path(&self, inc: &str, span: Span) -> Result<String, Diagnostic> {
+        if std::fs::exists(inc).unwrap_or(false) {
+            return Ok(inc.into());
+        }
+
+        let cwd_path = Path::new(&self.cwd).join(inc);
+        if std::fs::exists(cwd_path.clone()).unwrap_or(false) {
+            return Ok(cwd_path.to_string_lossy().to_string());
+        }
+
+        for path in &self.include_paths {
+            let inc_path = Path::new(path).join(inc);
+            if std::fs::exists(inc_path.clone()).unwrap_or(false) {
+                return Ok(inc_path.to_string_lossy().to_string());
+            }
+        }
+
+        Err(Diagnostic::error(codes::INCLUDE_NOT_FOUND, format!("failed to find included file \"{}\"", inc))
+            .with_label(span, "included here")
+            .with_note(format!("searched the current directory, {} and the include paths", self.cwd)))
+    }
+
+    fn check_condition(&mut self, expr: &mut Expr) {
+        let ty = self.check_value(expr, Some(&Ty::Bool));
+        if !ty.compatible(&Ty::Bool) {
+            self.mismatch(&Ty::Bool, &ty, expr.span);
+        }
+    }
+
+    /// Checks an expression whose result is used, so it can't be `void`
+    fn check_value(&mut self, expr: &mut Expr, hint: Option<&Ty>) -> Ty {
+        let ty = self.check_expr(expr, hint);
+        if ty.is_void() {
+            self.error(Diagnostic::error(codes::VOID_VALUE, "expression does not produce a value")
+                .with_label(expr.span, "this has type `void`"));
+            expr.ty = Some(Ty::Error);
+            return Ty::Error;
+        }
+        ty
+    }
+
+    /// `hint` is the type the context expects, untyped literals take it on when they can
+    fn check_expr(&mut self, expr: &mut Expr, hint: Option<&Ty>) -> Ty {
+        let ty = self.infer_expr(expr, hint);
+        expr.ty = Some(ty.clone());
+        ty
+    }
+
+    /// Checks a value that is stored as `expected`, numbers that fit in it without loss are widened to it.
+    /// Returns the type of the value afterwards, it's up to the caller to report a mismatch
+    fn check_coerced(&mut self, expr: &mut Expr, expected: &Ty) -> Ty {
+        let found = self.check_value(expr, Some(expected));
+        if found != *expected && found.widens_to(expected) {
+            widen(expr, expected);
+            return expected.clone();
+        }
+
+        // A `[]mut T` is laid out like a `[]T`, it just gives up being able to write
+        if let (Ty::Slice { elem, mutable: true }, Ty::Slice { elem: to, mutable: false }) = (&found, expected) && elem == to {
+            return expected.clone();
+        }
+        found
+    }
+
+    fn check_literal(&mut self, ty: &Ty, fits: bool, span: Span) {
+        if !fits {
+            self.error(Diagnostic::error(codes::LITERAL_OUT_OF_RANGE, format!("literal out of range for `{}`", ty))
+                .with_label(span, format!("does not fit in `{}`", ty)));
+        }
+    }
+
+    fn infer_expr(&mut self, expr: &mut Expr, hint: Option<&Ty>) -> Ty {
+        let span = expr.span;
+        match &mut expr.kind {
+            ExprKind::Int(value, suffix) => {
+                // Whole numbers are fine as floats too, so `x ^^ 2` works for a float `x`.
+                // A suffix decides the type no matter what the context expects
+                match suffix.as_ref().or(hint) {
+                    Some(ty @ Ty::Int { .. }) => {
+                        self.check_literal(ty, ty.holds_int(*value), span);
+                        ty.clone()
+                    },
+                    Some(ty @ Ty::Float { .. }) => {
+                        self.check_literal(ty, ty.holds_float(*value as f64), span);
+                        ty.clone()
+                    },
+                    _ => {
+                        let ty = Ty::Int { bits: 32, signed: true };
+                        self.check_literal(&ty, ty.holds_int(*value), span);
+                        ty
+                    },
+                }
+            },
+            ExprKind::Float(value, suffix) => {
+                let ty = match suffix.as_ref().or(hint) {
+                    Some(ty @ Ty::Float { .. }) => ty.clone(),
+                    _ => Ty::Float { bits: 32 },
+                };
+                self.check_literal(&ty, ty.holds_float(*value), span);
+                ty
+            },
+            ExprKind::String(_) => Ty::from_name("string").unwrap(),
+            // `'a'` is also a number where one is expected, so `bytes[i] == 'a'` works on a string
+            ExprKind::Char(value) => match hint {
+                Some(ty @ Ty::Int { bits, .. }) => {
+                    // Other characters take more than one byte of UTF-8, so only ASCII fits a byte
+                    let fits = ty.holds_int(*value as i128) && (*bits > 8 || value.is_ascii());
+                    self.check_literal(ty, fits, span);
+                    ty.clone()
+                },
+                _ => Ty::Char,
+            },
+            ExprKind::Bool(_) => Ty::Bool,
+            ExprKind::Symbol(name) => match self.scope.resolve(name) {
+                Some(Symbol::Variable { ty, .. }) => ty.clone(),
+                Some(_) => {
+                    let name = name.clone();
+                    self.error(Diagnostic::error(codes::NOT_A_VALUE, format!("`{}` is not a value", name))
+                        .with_label(span, "expected a variable"));
+                    Ty::Error
+                },
+                None => {
+                    let name = name.clone();
+                    self.error(Diagnostic::error(codes::UNRESOLVED_SYMBOL, format!("cannot find `{}` in this scope", name))
+                        .with_label(span, "not found in this scope"));
+                    Ty::Error
+                },
+            },
+            ExprKind::Binary { left, op, right } => {
+                let op = op.clone();
+                // Arithmetic produces the type of its operands so the context carries through to them,
+                // and an untyped literal operand takes the type of the other side
+                let hint = if is_arithmetic(&op) || is_bitwise(&op) { hint } else { None };
+                let (mut lt, mut rt) = if is_untyped(left) && !is_untyped(right) {
+                    let rt = self.check_value(right, hint);
+                    (self.check_value(left, Some(&rt)), rt)
+                } else {
+                    let lt = self.check_value(left, hint);
+                    (lt.clone(), self.check_value(right, Some(&lt)))
+                };
+
+                if lt != rt && lt.widens_to(&rt) {
+                    widen(left, &rt);
+                    lt = rt.clone();
+                } else if lt != rt && rt.widens_to(&lt) {
+                    widen(right, &lt);
+                    rt = lt.clone();
+                }
+
+                self.check_binary(&op, &lt, &rt, left.span, right.span, span)
+            },
+            ExprKind::Prefix { op, right } => {
+                let hint = if matches!(op, Token::BitNot | Token::Minus) { hint } else { None };
+                let ty = self.check_value(right, hint);
+                match op {
+                    Token::Not => {
+                        if !ty.compatible(&Ty::Bool) {
+                            self.mismatch(&Ty::Bool, &ty, right.span);
+                        }
+                        Ty::Bool
+                    },
+                    Token::Minus => {
+                        if !ty.is_error() && !ty.is_float() && !ty.is_signed() {
+                            self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot negate `{}`", ty))
+                                .with_label(span, format!("not supported for `{}`", ty)));
+                            return Ty::Error;
+                        }
+                        ty
+                    },
+                    Token::BitNot => {
+                        if !ty.is_int() && !ty.is_error() {
+                            self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot apply `~` to `{}`", ty))
+                                .with_label(span, format!("not supported for `{}`", ty)));
+                            return Ty::Error;
+                        }
+                        ty
+                    },
+                    _ => {
+                        let op = op.clone();
+                        self.error(Diagnostic::error(codes::UNSUPPORTED, format!("prefix operator {} is not supported yet", op))
+                            .with_label(span, "unsupported operator"));
+                        Ty::Error
+                    },
+                }
+            },
+            ExprKind::Ternary { condition, then, _else } => {
+                self.check_condition(condition);
+
+                let (mut then_ty, mut else_ty) = if is_untyped(then) && !is_untyped(_else) {
+                    let else_ty = self.check_value(_else, hint);
+                    (self.check_value(then, Some(&else_ty)), else_ty)
+                } else {
+                    let then_ty = self.check_value(then, hint);
+                    (then_ty.clone(), self.check_value(_else, Some(&then_ty)))
+                };
+
+                if then_ty != else_ty && then_ty.widens_to(&else_ty) {
+                    widen(then, &else_ty);
+                    then_ty = else_ty.clone();
+                } else if then_ty != else_ty && else_ty.widens_to(&then_ty) {
+                    widen(_else, &then_ty);
+                    else_ty = then_ty.clone();
+                }
+
+                if !then_ty.compatible(&else_ty) {
+                    self.error(Diagnostic::error(codes::MISMATCHED_TYPES, "`?` arms have incompatible types")
+                        .with_label(span, format!("`{}` and `{}` have no common type", then_ty, else_ty))
+                        .with_secondary(then.span, format!("this is `{}`", then_ty))
+                        .with_secondary(_else.span, format!("this is `{}`", else_ty)));
+                    return Ty::Error;
+                }
+
+                if then_ty.is_error() { else_ty } else { then_ty }
+            },
+            ExprKind::Increment { target, decrement, .. } => {
+                let ty = self.check_value(target, None);
+                self.check_assignable(target, if *decrement { "decrement" } else { "increment" });
+                if !ty.is_numeric() && !ty.is_error() {
+                    let op = if *decrement { "--" } else { "++" };
+                    self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot apply `{}` to `{}`", op, ty))
+                        .with_label(span, format!("not supported for `{}`", ty)));
+                    return Ty::Error;
+                }
+                ty
+            },
+            ExprKind::Array(elements) => {
+                // Elements take the element type the context expects, or else the type of the first one
+                let mut elem = match hint {
+                    Some(Ty::Array { elem, .. }) => Some((**elem).clone()),
+                    _ => None,
+                };
+
+                for element in elements.iter_mut() {
+                    match &elem {
+                        Some(expected) => {
+                            let found = self.check_coerced(element, expected);
+                            if !found.compatible(expected) {
+                                self.mismatch(expected, &found, element.span);
+                            }
+                        },
+                        None => elem = Some(self.check_value(element, None)),
+                    }
+                }
+
+                match elem {
+                    Some(Ty::Error) => Ty::Error,
+                    Some(elem) => Ty::Array { len: elements.len(), elem: Box::new(elem) },
+                    None => {
+                        self.error(Diagnostic::error(codes::TYPE_ANNOTATIONS_NEEDED, "cannot infer the type of an empty array")
+                            .with_label(span, "element type unknown")
+                            .with_note("give the variable a type such as `[0]i32`"));
+                        Ty::Error
+                    },
+                }
+            },
+            ExprKind::Index { target, index } => {
+                let tt = self.check_value(target, None);
+                self.check_index(index);
+
+                if let (Some(len), ExprKind::Int(i, _)) = (tt.array_len(), &index.kind) && *i >= 0 && *i as usize >= len {
+                    self.error(Diagnostic::error(codes::INDEX_OUT_OF_BOUNDS, format!("index {} is out of bounds for `{}`", i, tt))
+                        .with_label(index.span, format!("the length is {}", len)));
+                }
+
+                match tt.element() {
+                    Some(elem) => elem.clone(),
+                    None if tt.is_error() => Ty::Error,
+                    None => {
+                        self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot index into `{}`", tt))
+                            .with_label(target.span, format!("this has type `{}`", tt)));
+                        Ty::Error
+                    },
+                }
+            },
+            ExprKind::Slice { target, start, end } => {
+                let tt = self.check_value(target, None);
+                for bound in [&mut *start, &mut *end].into_iter().flatten() {
+                    self.check_index(bound);
+                }
+
+                let bound = |b: &Option<Box<Expr>>| match b.as_deref().map(|b| &b.kind) {
+                    Some(ExprKind::Int(i, _)) if *i >= 0 => Some(*i as usize),
+                    _ => None,
+                };
+                if let (Some(len), Some(end_at)) = (tt.array_len(), bound(end)) && end_at > len {
+                    self.error(Diagnostic::error(codes::INDEX_OUT_OF_BOUNDS, format!("slice end {} is out of bounds for `{}`", end_at, tt))
+                        .with_label(end.as_ref().unwrap().span, format!("the length is {}", len)));
+                }
+                if let (Some(start_at), Some(end_at)) = (bound(start), bound(end)) && start_at > end_at {
+                    self.error(Diagnostic::error(codes::INDEX_OUT_OF_BOUNDS, format!("slice starts at {} but ends at {}", start_at, end_at))
+                        .with_label(span, "start is past the end"));
+                }
+
+                // A slice can write to the elements it views only when the array could be written itself
+                let mutable = match &tt {
+                    Ty::Slice { mutable, .. } => *mutable,
+                    Ty::Ref(_) => true,
+                    _ => self.assignable(target, "slice").is_ok(),
+                };
+
+                match (tt.element(), &tt) {
+                    (Some(elem), _) => Ty::Slice { elem: Box::new(elem.clone()), mutable },
+                    // Memory behind a plain reference has no known length, so it has to be given
+                    (None, Ty::Ref(inner)) => {
+                        if end.is_none() {
+                            self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("slicing `{}` needs an end", tt))
+                                .with_label(span, "the length behind a reference isn't known"));
+                        }
+                        Ty::Slice { elem: inner.clone(), mutable }
+                    },
+                    (None, Ty::Error) => Ty::Error,
+                    (None, _) => {
+                        self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot slice `{}`", tt))
+                            .with_label(target.span, format!("this has type `{}`", tt)));
+                        Ty::Error
+                    },
+                }
+            },
+            ExprKind::Call { name, args } => {
+                let name = name.clone();
+                self.check_call(&name, args, span)
+            },
+            ExprKind::MethodCall { receiver, name, args, by_ref } => {
+                let ty = self.check_value(receiver, None);
+                let this = match ty.struct_name() {
+                    Some(this) => this.to_string(),
+                    None if ty.is_error() => {
+                        for arg in args.iter_mut() {
+                            self.check_value(arg, None);
+                        }
+                        return Ty::Error;
+                    },
+                    None => {
+                        self.error(Diagnostic::error(codes::UNRESOLVED_FIELD, format!("type `{}` has no methods", ty))
+                            .with_label(receiver.span, format!("this has type `{}`", ty)));
+                        return Ty::Error;
+                    },
+                };
+
+                let sig = match self.scope.resolve(&method_symbol(&this, name)) {
+                    Some(Symbol::Function(sig)) => sig.clone(),
+                    _ => {
+                        let message = format!("no method named `{}` on type `{}`", name, this);
+                        self.error(Diagnostic::error(codes::UNRESOLVED_FIELD, message)
+                            .with_label(span, "method not found"));
+                        return Ty::Error;
+                    },
+                };
+
+                *by_ref = matches!(sig.params.first(), Some(Ty::Ref(_)));
+                // Temporaries are given a slot of their own, so only named receivers need to be mutable
+                if *by_ref && matches!(receiver.kind, ExprKind::Symbol(_) | ExprKind::Access { .. } | ExprKind::Index { .. }) && !matches!(ty, Ty::Ref(_)) {
+                    self.check_assignable(receiver, &format!("call `&this` method `{}` on", name));
+                }
+
+                let name = name.clone();
+                let params = sig.params.iter().skip(1).cloned().collect::<Vec<_>>();
+                self.check_arguments(&name, &params, args, sig.span, span);
+                sig.ret
+            },
+            ExprKind::Assignment { assignee, right } => {
+                let target = self.check_expr(assignee, None);
+                let value = self.check_coerced(right, &target);
+                self.check_assignable(assignee, "assign to");
+
+                if !value.compatible(&target) {
+                    self.mismatch(&target, &value, right.span);
+                }
+                Ty::Void
+            },
+            ExprKind::CompoundAssignment { assignee, op, right } => {
+                let op = op.clone();
+                let target = self.check_expr(assignee, None);
+                self.check_assignable(assignee, "assign to");
+
+                let mut value = self.check_value(right, Some(&target));
+                if value != target && value.widens_to(&target) {
+                    widen(right, &target);
+                    value = target.clone();
+                }
+
+                let result = self.check_binary(&op, &target, &value, assignee.span, right.span, span);
+                if !result.compatible(&target) {
+                    self.mismatch(&target, &result, span);
+                }
+                Ty::Void
+            },
+            ExprKind::StructInitialize { name, fields } => {
+                let name = name.clone();
+                let Some(def) = self.structs.get(&name).cloned() else {
+                    self.error(Diagnostic::error(codes::UNRESOLVED_TYPE, format!("cannot find struct `{}`", name))
+                        .with_label(span, "not found in this scope"));
+                    for field in fields.iter_mut() {
+                        if let StmtKind::StructInitField { value, .. } = &mut field.kind {
+                            self.check_value(value, None);
+                        }
+                    }
+                    return Ty::Error;
+                };
+
+                let mut seen: Vec<(String, Span)> = vec![];
+                for field in fields.iter_mut() {
+                    let StmtKind::StructInitField { name: field_name, value } = &mut field.kind else {
+                        panic!("Expected struct init field");
+                    };
+
+                    if let Some((_, previous)) = seen.iter().find(|(n, _)| n == field_name) {
+                        let previous = *previous;
+                        let field_name = field_name.clone();
+                        self.error(Diagnostic::error(codes::DUPLICATE_FIELD, format!("field `{}` is initialized more than once", field_name))
+                            .with_label(field.span, "initialized again here")
+                            .with_secondary(previous, "first initialized here"));
+                    } else {
+                        seen.push((field_name.clone(), field.span));
+                    }
+
+                    let found = match def.field(field_name) {
+                        Some((_, expected)) => self.check_coerced(value, expected),
+                        None => self.check_value(value, None),
+                    };
+                    match def.field(field_name) {
+                        Some((_, expected)) => if !found.compatible(expected) {
+                            self.mismatch(expected, &found, value.span);
+                        },
+                        None => {
+                            let field_name = field_name.clone();
+                            self.error(Diagnostic::error(codes::UNRESOLVED_FIELD, format!("struct `{}` has no field named `{}`", name, field_name))
+                                .with_label(field.span, "unknown field")
+                                .with_secondary(def.span, format!("`{}` declared here", name)));
+                        },
+                    }
+                }
+
+                let missing = def.fields.iter()
+                    .filter(|(field, _)| !seen.iter().any(|(n, _)| n == field))
+                    .map(|(field, _)| format!("`{}`", field))
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    self.error(Diagnostic::error(codes::MISSING_FIELD, format!("missing field{} {} in initializer of `{}`", if missing.len() == 1 { "" } else { "s" }, missing.join(", "), name))
+                        .with_label(span, format!("missing {}", missing.join(", ")))
+                        .with_secondary(def.span, format!("`{}` declared here", name)));
+                }
+
+                Ty::Struct(name)
+            },
+            ExprKind::New(value) => match self.check_value(value, None) {
+                Ty::Error => Ty::Error,
+                ty => Ty::Ref(Box::new(ty)),
+            },
+            ExprKind::Access { lhs, rhs } => {
+                let lt = self.check_value(lhs, None);
+                let ExprKind::Symbol(field) = &rhs.kind else {
+                    unreachable!("the parser only builds member accesses with a field name on the right");
+                };
+
+                // Arrays and slices have a single field, their length
+                if lt.element().is_some() && field == "len" {
+                    let ty = Ty::from_name("usize").unwrap();
+                    rhs.ty = Some(ty.clone());
+                    return ty;
+                }
+
+                let ty = match lt.struct_name() {
+                    Some(name) => match self.structs.get(name).and_then(|d| d.field(field)) {
+                        Some((_, ty)) => ty.clone(),
+                        None => {
+                            let message = format!("no field `{}` on type `{}`", field, name);
+                            self.error(Diagnostic::error(codes::UNRESOLVED_FIELD, message)
+                                .with_label(rhs.span, "unknown field"));
+                            Ty::Error
+                        },
+                    },
+                    None if lt.is_error() => Ty::Error,
+                    None => {
+                        self.error(Diagnostic::error(codes::UNRESOLVED_FIELD, format!("type `{}` has no fields", lt))
+                            .with_label(lhs.span, format!("this has type `{}`", lt)));
+                        Ty::Error
+                    },
+                };
+
+                rhs.ty = Some(ty.clone());
+                ty
+            },
+            ExprKind::Range { .. } => {
+                self.error(Diagnostic::error(codes::UNSUPPORTED, "ranges can only be used in `for` loops")
+                    .with_label(span, "not valid here"));
+                Ty::Error
+            },
+            ExprKind::Cast { expr: inner, target } => {
+                let to = self.resolve_type(target);
+                let from = self.check_value(inner, Some(&to));
+                if !from.is_error() && !to.is_error() && !from.casts_to(&to) {
+                    self.error(Diagnostic::error(codes::INVALID_CAST, format!("cannot cast `{}` as `{}`", from, to))
+                        .with_label(span, "invalid cast")
+                        .with_secondary(inner.span, format!("this is `{}`", from)));
+                }
+                to
+            },
+            ExprKind::Widen(_) => unreachable!("widenings are only inserted around expressions that were already checked"),
+            ExprKind::StmtHack(_) => {
+                self.error(Diagnostic::error(codes::UNSUPPORTED, "declarations are not allowed inside expressions")
+                    .with_label(span, "move this declaration to the top level"));
+                Ty::Error
+            },
+        }
+    }
+
+    /// Reports targets that can't be written to, `action` describes what is being done to it
+    fn check_assignable(&mut self, target: &Expr, action: &str) {
+        if let Err(error) = self.assignable(target, action) {
+            self.error(error);
+        }
+    }
+
+    /// Fields can be written when the variable holding the struct is mutable or when they're
+    /// reached through a reference, which is how `&this` methods change their receiver.
+    /// Elements reached through a slice can be written when it is a `[]mut T`
+    fn assignable(&self, target: &Expr, action: &str) -> Result<(), Diagnostic> {
+        if let ExprKind::Access { lhs, .. } = &target.kind && lhs.ty.as_ref().is_some_and(|ty| ty.element().is_some()) {
+            return Err(Diagnostic::error(codes::INVALID_ASSIGNMENT_TARGET, format!("cannot {} the length of `{}`", action, lhs.ty()))
+                .with_label(target.span, "the length is read-only"));
+        }
+
+        let mut root = target;
+        while let ExprKind::Access { lhs, .. } | ExprKind::Index { target: lhs, .. } = &root.kind {
+            match &lhs.ty {
+                // Both point into memory they don't own, so the variable holding them doesn't matter
+                Some(Ty::Ref(_) | Ty::Slice { mutable: true, .. }) => return Ok(()),
+                Some(ty @ Ty::Slice { elem, mutable: false }) => {
+                    return Err(Diagnostic::error(codes::IMMUTABLE_ASSIGNMENT, format!("cannot {} an element of read-only slice `{}`", action, ty))
+                        .with_label(target.span, format!("cannot {} this", action))
+                        .with_note(format!("only a `[]mut {}` can be written through, which slicing a `mut` variable gives", elem)));
+                },
+                _ => root = lhs,
+            }
+        }
+
+        let ExprKind::Symbol(name) = &root.kind else {
+            return Err(Diagnostic::error(codes::INVALID_ASSIGNMENT_TARGET, format!("cannot {} this expression", action))
+                .with_label(target.span, "expected a variable, field or element"));
+        };
+
+        if let Some(Symbol::Variable { mutable: false, span, .. }) = self.scope.resolve(name) {
+            let message = if root.span == target.span {
+                format!("cannot {} immutable variable `{}`", action, name)
+            } else if let ExprKind::Index { .. } = target.kind {
+                format!("cannot {} an element of immutable variable `{}`", action, name)
+            } else {
+                format!("cannot {} a field of immutable variable `{}`", action, name)
+            };
+            let declared = *span;
+            let note = if name == "this" {
+                "take `&this` to let a method change its receiver"
+            } else {
+                "only variables declared with `mut` can be changed"
+            };
+            return Err(Diagnostic::error(codes::IMMUTABLE_ASSIGNMENT, message)
+                .with_label(target.span, format!("cannot {} this", action))
+                .with_secondary(declared, format!("`{}` declared here", name))
+                .with_note(note));
+        }
+        Ok(())
+    }
+
+    /// Indices and slice bounds can be any integer type, untyped literals become `usize`
+    fn check_index(&mut self, index: &mut Expr) {
+        let ty = self.check_value(index, Some(&Ty::from_name("usize").unwrap()));
+        if !ty.is_int() && !ty.is_error() {
+            self.error(Diagnostic::error(codes::MISMATCHED_TYPES, "array index must be an integer")
+                .with_label(index.span, format!("this is `{}`", ty)));
+        }
+    }
+
+    fn check_call(&mut self, name: &str, args: &mut [Expr], span: Span) -> Ty {
+        let sig = match self.scope.resolve(name) {
+            Some(Symbol::Function(sig)) => Some(sig.clone()),
+            Some(_) => {
+                self.error(Diagnostic::error(codes::NOT_CALLABLE, format!("`{}` is not a function", name))
+                    .with_label(span, "called here"));
+                None
+            },
+            None => {
+                self.error(Diagnostic::error(codes::UNRESOLVED_SYMBOL, format!("cannot find function `{}` in this scope", name))
+                    .with_label(span, "not found in this scope"));
+                None
+            },
+        };
+
+        let Some(sig) = sig else {
+            for arg in args.iter_mut() {
+                self.check_value(arg, None);
+            }
+            return Ty::Error;
+        };
+
+        self.check_arguments(name, &sig.params, args, sig.span, span);
+        sig.ret
+    }
+
+    fn check_arguments(&mut self, name: &str, params: &[Ty], args: &mut [Expr], defined: Span, span: Span) {
+        if params.len() != args.len() {
+            self.error(Diagnostic::error(codes::ARGUMENT_COUNT, format!(
+                "function `{}` takes {} argument{} but {} {} supplied",
+                name, params.len(), if params.len() == 1 { "" } else { "s" }, args.len(), if args.len() == 1 { "was" } else { "were" },
+            ))
+                .with_label(span, "wrong number of arguments")
+                .with_secondary(defined, "function defined here"));
+        }
+
+        for (i, arg) in args.iter_mut().enumerate() {
+            let Some(expected) = params.get(i) else {
+                self.check_value(arg, None);
+                continue;
+            };
+
+            let found = self.check_coerced(arg, expected);
+            if !found.compatible(expected) {
+                self.mismatch(expected, &found, arg.span);
+            }
+        }
+    }
+
+    fn check_binary(&mut self, op: &Token, lt: &Ty, rt: &Ty, lspan: Span, rspan: Span, span: Span) -> Ty {
+        let comparison = matches!(op, Token::Less | Token::LessOrEqual | Token::Greater | Token::GreaterOrEqual | Token::Equals | Token::NotEquals);
+        let logical = matches!(op, Token::And | Token::Or);
+        let bitwise = is_bitwise(op);
+        // Arithmetic and bitwise operators produce the type of their operands
+        let arithmetic = is_arithmetic(op) || bitwise;
+
+        if !comparison && !logical && !arithmetic {
+            self.error(Diagnostic::error(codes::UNSUPPORTED, format!("operator {} is not supported yet", op))
+                .with_label(span, "unsupported operator"));
+            return Ty::Error;
+        }
+
+        let result = if arithmetic { lt.clone() } else { Ty::Bool };
+        if lt.is_error() || rt.is_error() {
+            return if arithmetic { Ty::Error } else { Ty::Bool };
+        }
+
+        if lt != rt {
+            self.error(Diagnostic::error(codes::MISMATCHED_TYPES, format!("mismatched types for {}", op))
+                .with_label(span, format!("no implementation for `{} {} {}`", lt, op, rt))
+                .with_secondary(lspan, format!("this is `{}`", lt))
+                .with_secondary(rspan, format!("this is `{}`", rt)));
+            return if arithmetic { Ty::Error } else { Ty::Bool };
+        }
+
+        let valid = if logical {
+            *lt == Ty::Bool
+        } else if matches!(op, Token::Equals | Token::NotEquals) {
+            lt.is_numeric() || matches!(lt, Ty::Bool | Ty::Char | Ty::Ref(_))
+        } else if comparison {
+            lt.is_numeric() || *lt == Ty::Char
+        } else if bitwise {
+            lt.is_int() || (*lt == Ty::Bool && !matches!(op, Token::ShiftLeft | Token::ShiftRight))
+        } else {
+            lt.is_numeric()
+        };
+
+        if !valid {
+            self.error(Diagnostic::error(codes::INVALID_OPERATION, format!("cannot apply {} to `{}`", op, lt))
+                .with_label(span, format!("not supported for `{}`", lt)));
+            return if arithmetic { Ty::Error } else { Ty::Bool };
+        }
+
+        result
+    }
+}
+
+/// Two paths to the same file are the same once canonicalized, a path that can't be is kept as is
+fn canonical_path(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+fn is_arithmetic(op: &Token) -> bool {
+    matches!(op, Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Mod | Token::Power(_))
+}
+
+fn is_bitwise(op: &Token) -> bool {
+    matches!(op, Token::Reference | Token::BitOr | Token::BitXor | Token::ShiftLeft | Token::ShiftRight)
+}
+
+/// Whether `expr` is made up only of numeric and character literals, so its type comes from where it's used
+fn is_untyped(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Int(_, None) | ExprKind::Float(_, None) | ExprKind::Char(_) => true,
+        ExprKind::Binary { left, op, right } => (is_arithmetic(op) || is_bitwise(op)) && is_untyped(left) && is_untyped(right),
+        ExprKind::Prefix { op: Token::BitNot | Token::Minus, right } => is_untyped(right),
+        ExprKind::Ternary { then, _else, .. } => is_untyped(then) && is_untyped(_else),
+        _ => false,
+    }
+}
+
+/// Wraps `expr` in an implicit conversion to `to`
+fn widen(expr: &mut Expr, to: &Ty) {
+    let span = expr.span;
+    let inner = std::mem::replace(expr, Expr::new(ExprKind::Bool(false), span));
+    *expr = Expr::new(ExprKind::Widen(Box::new(inner)), span);
+    expr.ty = Some(to.clone());
+}
+
+/// Name a method is defined under, which is also its symbol in the output
+pub fn method_symbol(this: &str, method: &str) -> String {
+    format!("{}.{}", this, method)
+}
+
+/// Names can't contain a `.`, so only the symbols of methods do
+pub fn is_method_symbol(symbol: &str) -> bool {
+    symbol.contains('.')
+}
+
+/// Whether every path through `stmt` ends in a `return`
+fn always_returns(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Return { .. } => true,
+        StmtKind::Block(body) => body.iter().any(always_returns),
+        StmtKind::If { body, _else: Some(_else), .. } => always_returns(body) && always_returns(_else),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_in(source: &str, path: &str, cwd: Option<String>) -> Vec<&'static str> {
+        let mut diagnostics = Diagnostics::new();
+        let file = diagnostics.add_file(path.into(), source.into());
+        let tokens = lexer::tokenize(source, file, &mut diagnostics);
+        let mut ast = Parser::parse(tokens, &mut diagnostics);
+        assert!(!diagnostics.has_errors(), "failed to parse: {:?}", diagnostics.diagnostics());
+        Checker::check(&mut ast, path, vec![], cwd, &mut diagnostics);
+        diagnostics.diagnostics().iter().map(|d| d.code).collect()
+    }
+
+    /// Codes of everything reported for `body` as the body of `main`
+    fn check(body: &str) -> Vec<&'static str> {
+        check_in(&format!("main -> sub() {{\n{}\n}}\n", body), "test.ig", None)
+    }
+
+    /// A scratch directory holding `files`, removed again by `remove_dir_all`
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ignis-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            std::fs::write(dir.join(file), source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn mismatched_types() {
+        assert_eq!(check("let x: i32 = true;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let x: bool = 1 + 2;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("if 1 { }"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let x = y;"), [codes::UNRESOLVED_SYMBOL]);
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        assert_eq!(check("let a: i32 = true;\nlet b = c;\nlet d: bool = 1;"),
+            [codes::MISMATCHED_TYPES, codes::UNRESOLVED_SYMBOL, codes::MISMATCHED_TYPES]);
+    }
+
+    #[test]
+    fn calls() {
+        let source = "f -> sub(a i32) i32 { return a; }\nmain -> sub() {\n";
+        let call = |body: &str| check_in(&format!("{}{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(call("let x = f(1);"), Vec::<&str>::new());
+        assert_eq!(call("let x = f(1, 2);"), [codes::ARGUMENT_COUNT]);
+        assert_eq!(call("let x: bool = f(1);"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(call("let x = f(true);"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(call("let x = g(1);"), [codes::UNRESOLVED_SYMBOL]);
+    }
+
+    #[test]
+    fn returns() {
+        assert_eq!(check_in("f -> sub() i32 { }", "test.ig", None), [codes::MISSING_RETURN]);
+        assert_eq!(check_in("f -> sub() { return 1; }", "test.ig", None), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check_in("f -> sub() i32 { return true; }", "test.ig", None), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check_in("f -> sub() i32 { return; }", "test.ig", None), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check_in("f -> sub() { return; }", "test.ig", None), Vec::<&str>::new());
+        assert_eq!(check_in("f -> sub(a bool) i32 { if a { return 1; } else { return 2; } }", "test.ig", None), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn loop_control() {
+        assert_eq!(check("outer: while true { while true { break outer; } continue; }"), Vec::<&str>::new());
+        assert_eq!(check("break;"), [codes::OUTSIDE_LOOP]);
+        assert_eq!(check("if true { continue; }"), [codes::OUTSIDE_LOOP]);
+        assert_eq!(check("while true { break inner; }"), [codes::UNRESOLVED_LABEL]);
+    }
+
+    #[test]
+    fn for_loops() {
+        assert_eq!(check("for i in [0..5] { let x = i + 1; } let n = 3; for i in [0..=n : 2] { }"), Vec::<&str>::new());
+        assert_eq!(check("for i in [0..5 : 0] { }"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("for i in [0..true] { }"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("for i in [0.5..2] { }"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("for i in [0..5] { } let x = i;"), [codes::UNRESOLVED_SYMBOL]);
+    }
+
+    #[test]
+    fn methods() {
+        let source = "P -> struct {\n    x i32,\n    get -> sub(this) i32 { return this.x; }\n    add -> sub(&this, n i32) { this.x = this.x + n; }\n}\nmain -> sub() {\n";
+        let call = |body: &str| check_in(&format!("{}{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(call("mut p = P { x: 1 }; p.add(2); let x: i32 = p.get();"), Vec::<&str>::new());
+        assert_eq!(call("let p = P { x: 1 }; p.set(2);"), [codes::UNRESOLVED_FIELD]);
+        assert_eq!(call("mut p = P { x: 1 }; p.add(1, 2);"), [codes::ARGUMENT_COUNT]);
+        assert_eq!(call("let p = P { x: 1 }; let x: bool = p.get();"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(call("let n = 1; n.get();"), [codes::UNRESOLVED_FIELD]);
+        assert_eq!(check_in("P -> struct {\n    x i32,\n    get -> sub() i32 { return 1; }\n}\n", "test.ig", None), [codes::INVALID_RECEIVER]);
+    }
+
+    #[test]
+    fn methods_follow_their_struct_through_includes() {
+        let dir = write_files("include-methods", &[
+            ("c.ig", "P -> struct {\n    x i32,\n    get -> sub(this) i32 { return this.x; }\n}\nhelper -> sub() i32 { return 1; }\n"),
+            ("b.ig", "include \"c.ig\"\nmake -> sub() P { return P { x: 1 }; }\n"),
+        ]);
+        let cwd = Some(dir.to_string_lossy().to_string());
+        let methods = check_in("include \"b.ig\"\nmain -> sub() {\n    let x: i32 = make().get() + make().x;\n}\n", "main.ig", cwd.clone());
+        let functions = check_in("include \"b.ig\"\nmain -> sub() {\n    let x = helper();\n}\n", "main.ig", cwd);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(methods, Vec::<&str>::new());
+        assert_eq!(functions, [codes::UNRESOLVED_SYMBOL]);
+    }
+
+    #[test]
+    fn declarations_can_be_used_before_they_are_written() {
+        let source = "main -> sub() {\n    let n = even(4);\n    let c = C { n: 1 };\n}\n\
+            even -> sub(n i32) bool { if n == 0 { return true; } return odd(n - 1); }\n\
+            odd -> sub(n i32) bool { if n == 0 { return false; } return even(n - 1); }\n\
+            A -> struct {\n    n i32,\n    b &B,\n}\n\
+            B -> struct {\n    a &A,\n}\n\
+            C -> struct {\n    n i32,\n}\n";
+        assert_eq!(check_in(source, "test.ig", None), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn structs_cannot_contain_themselves() {
+        assert_eq!(check_in("A -> struct {\n    a A,\n}\n", "test.ig", None), [codes::RECURSIVE_TYPE]);
+        assert_eq!(check_in("A -> struct {\n    b B,\n}\nB -> struct {\n    a A,\n}\n", "test.ig", None), [codes::RECURSIVE_TYPE, codes::RECURSIVE_TYPE]);
+        assert_eq!(check_in("A -> struct {\n    a &A,\n}\n", "test.ig", None), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn declarations_only_at_the_top_level() {
+        assert_eq!(check("f -> sub() { }"), [codes::UNSUPPORTED]);
+    }
+
+    #[test]
+    fn struct_literal_fields() {
+        let source = "P -> struct {\n    x i32,\n}\nmain -> sub() {\n";
+        let init = |body: &str| check_in(&format!("{}{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(init("let p = P { x: 1 };"), Vec::<&str>::new());
+        assert_eq!(init("let p = P { x: true };"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(init("let p = P { x: 1, y: 1 };"), [codes::UNRESOLVED_FIELD]);
+        assert_eq!(init("let p = P { };"), [codes::MISSING_FIELD]);
+        assert_eq!(init("let p: &P = new P { x: 1 }; delete p;"), Vec::<&str>::new());
+        assert_eq!(init("let p = P { x: 1 }; delete p;"), [codes::INVALID_OPERATION]);
+    }
+
+    #[test]
+    fn include_cycles_are_reported() {
+        let dir = write_files("include-cycle", &[("a.ig", "include \"b.ig\"\n"), ("b.ig", "include \"a.ig\"\n")]);
+        let a = dir.join("a.ig").to_string_lossy().to_string();
+        let codes = check_in("include \"b.ig\"\n", &a, Some(dir.to_string_lossy().to_string()));
+        let itself = check_in("include \"a.ig\"\n", &a, Some(dir.to_string_lossy().to_string()));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(codes, [codes::INCLUDE_CYCLE]);
+        assert_eq!(itself, [codes::INCLUDE_CYCLE]);
+    }
+
+    #[test]
+    fn diamond_includes_are_checked_once() {
+        let dir = write_files("include-diamond", &[
+            ("b.ig", "include \"d.ig\"\nb -> sub() i32 { return d(); }\n"),
+            ("c.ig", "include \"d.ig\"\nc -> sub() i32 { return d(); }\n"),
+            ("d.ig", "d -> sub() i32 { return true; }\n"),
+        ]);
+        let source = "include \"b.ig\"\ninclude \"c.ig\"\nmain -> sub() {\n    let x = b() + c();\n}\n";
+        let mut diagnostics = Diagnostics::new();
+        let tokens = lexer::tokenize(source, 0, &mut diagnostics);
+        let mut ast = Parser::parse(tokens, &mut diagnostics);
+        Checker::check(&mut ast, "main.ig", vec![], Some(dir.to_string_lossy().to_string()), &mut diagnostics);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let codes: Vec<_> = diagnostics.diagnostics().iter().map(|d| d.code).collect();
+        assert_eq!(codes, [codes::MISMATCHED_TYPES]);
+
+        let StmtKind::Block(body) = &ast.kind else {
+            panic!("Expected the program to be a block");
+        };
+        let compiled: Vec<bool> = body[..2].iter().map(|include| {
+            let StmtKind::Include { module: Some(b_or_c), .. } = &include.kind else {
+                panic!("Expected a resolved include");
+            };
+            let StmtKind::Block(body) = &b_or_c.ast.kind else {
+                panic!("Expected the module to be a block");
+            };
+            let StmtKind::Include { module: Some(d), .. } = &body[0].kind else {
+                panic!("Expected a resolved include");
+            };
+            d.compile
+        }).collect();
+        assert_eq!(compiled, [true, false]);
+    }
+
+    #[test]
+    fn literals_take_the_expected_type() {
+        assert_eq!(check("let a: u8 = 255; let b: i64 = 1 + 2; let c: f64 = 1.5;"), Vec::<&str>::new());
+        assert_eq!(check("let a: u8 = 256;"), [codes::LITERAL_OUT_OF_RANGE]);
+        // Every literal has to fit on its own, even when the result would
+        assert_eq!(check("let a: i8 = 200 - 100;"), [codes::LITERAL_OUT_OF_RANGE]);
+        assert_eq!(check("let a: i32 = 1.5;"), [codes::MISMATCHED_TYPES]);
+    }
+
+    #[test]
+    fn numbers_widen_but_never_narrow() {
+        assert_eq!(check("let a: u8 = 1; let b: i32 = a; let c: u64 = a; let d: f32 = 1.5; let e: f64 = d;"), Vec::<&str>::new());
+        assert_eq!(check("let a: u32 = 1; let b: i32 = a;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a: i64 = 1; let b: i32 = a;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a: i32 = 1; let b: u32 = 2; let c = a + b;"), [codes::MISMATCHED_TYPES]);
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(check("let n = 300; let a = n as u8; let b = 1.5 as i32; let c = true as u8; let d: u64 = 2; let e = d as f32;"), Vec::<&str>::new());
+        assert_eq!(check("let s = \"a\"; let r = s as &u8; let p = r as u64; let q = p as &i8;"), Vec::<&str>::new());
+        assert_eq!(check("let s = \"a\"; let p = s as u64;"), [codes::INVALID_CAST]);
+        assert_eq!(check("let a = 1 as bool;"), [codes::INVALID_CAST]);
+        assert_eq!(check("let a = 1.5 as string;"), [codes::INVALID_CAST]);
+        assert_eq!(check("let a = true as f64;"), [codes::INVALID_CAST]);
+        assert_eq!(check("let a = 1 as Nope;"), [codes::UNRESOLVED_TYPE]);
+    }
+
+    #[test]
+    fn compound_assignment() {
+        assert_eq!(check("mut x = 1; x += 2; x <<= 1; x %= 3;"), Vec::<&str>::new());
+        assert_eq!(check("mut f = 1.5; f *= 2.0; f -= 1;"), Vec::<&str>::new());
+        assert_eq!(check("mut b = true; b += true;"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("mut x = 1; x += true;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("mut f = 1.5; f <<= 1;"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("mut a = 1; mut b = 2; a = b += 1;"), [codes::VOID_VALUE]);
+    }
+
+    #[test]
+    fn negation_and_increments() {
+        assert_eq!(check("let a: i8 = -128; let b = -a; let c = -1.5; mut d = 1; d++; --d;"), Vec::<&str>::new());
+        assert_eq!(check("let a: i8 = -129;"), [codes::LITERAL_OUT_OF_RANGE]);
+        assert_eq!(check("let a: u8 = 1; let b = -a;"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("mut b = true; b++;"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("let a = 1; (a + 1)++;"), [codes::INVALID_ASSIGNMENT_TARGET]);
+        assert_eq!(check("let a = 1 && true;"), [codes::MISMATCHED_TYPES]);
+    }
+
+    #[test]
+    fn ternaries() {
+        assert_eq!(check("let a: u8 = true ? 1 : 2; let b = false ? 1.5 : 2.5;"), Vec::<&str>::new());
+        assert_eq!(check("let a = 1 ? 2 : 3;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a = true ? 1 : false;"), [codes::MISMATCHED_TYPES]);
+    }
+
+    #[test]
+    fn immutable_bindings() {
+        assert_eq!(check("mut x = 1; x = 2; x += 1; x++;"), Vec::<&str>::new());
+        assert_eq!(check("let x = 1; x = 2;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let x = 1; x += 2;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let x = 1; x++;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check_in("f -> sub(n i32) { n = 2; }", "test.ig", None), [codes::IMMUTABLE_ASSIGNMENT]);
+    }
+
+    #[test]
+    fn fields_follow_their_variable() {
+        let source = "P -> struct {\n    x i32,\n    bump -> sub(&this) { this.x += 1; }\n    peek -> sub(this) i32 { return this.x; }\n}\nmain -> sub() {\n";
+        let call = |body: &str| check_in(&format!("{}{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(call("mut p = P { x: 0 }; p.x = 1; p.bump();"), Vec::<&str>::new());
+        assert_eq!(call("let p = P { x: 0 }; let x = p.peek();"), Vec::<&str>::new());
+        assert_eq!(call("let p = P { x: 0 }; p.x = 1;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(call("let p = P { x: 0 }; p.bump();"), [codes::IMMUTABLE_ASSIGNMENT]);
+        let by_value = "P -> struct {\n    x i32,\n    spoil -> sub(this) { this.x = 0; }\n}\n";
+        assert_eq!(check_in(by_value, "test.ig", None), [codes::IMMUTABLE_ASSIGNMENT]);
+    }
+
+    #[test]
+    fn shadowing_from_inner_blocks() {
+        assert_eq!(check("let x = 1; if true { let x = true; } let y: i32 = x;"), Vec::<&str>::new());
+        assert_eq!(check("let i = 1; for i in [0..3] { let j = i; }"), Vec::<&str>::new());
+        assert_eq!(check_in("f -> sub(n i32) { let n = true; }", "test.ig", None), Vec::<&str>::new());
+        assert_eq!(check("let x = 1; let x = 2;"), [codes::REDEFINITION]);
+        assert_eq!(check("while true { let y = 1; } let z = y;"), [codes::UNRESOLVED_SYMBOL]);
+    }
+
+    #[test]
+    fn access_chains() {
+        let source = "In -> struct {\n    v i32,\n}\nOut -> struct {\n    inner In,\n}\nmake -> sub() Out { return Out { inner: In { v: 1 } }; }\n";
+        let body = |body: &str| check_in(&format!("{}main -> sub() {{\n{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(body("mut o = make(); let a: i32 = o.inner.v; o.inner.v = 2; let b: i32 = make().inner.v;"), Vec::<&str>::new());
+        assert_eq!(body("let o = make(); o.inner.v = 2;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(body("let o = make(); let a = o.inner.w;"), [codes::UNRESOLVED_FIELD]);
+        assert_eq!(body("let o = make(); let a: bool = o.inner.v;"), [codes::MISMATCHED_TYPES]);
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(check("mut a = [1, 2, 3]; a[0] = 4; let b: i32 = a[2]; let c: [2]u8 = [5, 6];"), Vec::<&str>::new());
+        assert_eq!(check("let a = [1, 2, 3]; let b = a[3];"), [codes::INDEX_OUT_OF_BOUNDS]);
+        assert_eq!(check("let a = [1, 2, 3]; let b = a[true];"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a = [1, true];"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a: [2]i32 = [1, 2, 3];"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a = [];"), [codes::TYPE_ANNOTATIONS_NEEDED]);
+        assert_eq!(check("let a = 1; let b = a[0];"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("let a = [1, 2, 3]; a[0] = 4;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("mut t: i32 = 0; for x in [1, 2, 3] { t += x; }"), Vec::<&str>::new());
+        assert_eq!(check("for x in true { }"), [codes::UNSUPPORTED]);
+    }
+
+    #[test]
+    fn by_ref_methods_on_elements_need_a_mutable_array() {
+        let source = "P -> struct {\n    n i32,\n    bump -> sub(&this) { this.n += 1; }\n}\n";
+        let body = |body: &str| check_in(&format!("{}main -> sub() {{\n{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(body("mut a = [P { n: 1 }]; a[0].bump();"), Vec::<&str>::new());
+        assert_eq!(body("let a = [P { n: 1 }]; a[0].bump();"), [codes::IMMUTABLE_ASSIGNMENT]);
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(check("let a = [1, 2, 3]; let s: []i32 = a[1..]; let n: usize = s.len; let x: i32 = s[0];"), Vec::<&str>::new());
+        assert_eq!(check("let a = [1, 2, 3]; let s = a[..4];"), [codes::INDEX_OUT_OF_BOUNDS]);
+        assert_eq!(check("let a = [1, 2, 3]; let s = a[2..1];"), [codes::INDEX_OUT_OF_BOUNDS]);
+        assert_eq!(check("let a = 1; let s = a[..];"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("let a = [1, 2, 3]; let s: []u8 = a[..];"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("mut a = [1, 2, 3]; a.len = 2;"), [codes::INVALID_ASSIGNMENT_TARGET]);
+        assert_eq!(check("mut t = 0; let a = [1, 2, 3]; for x in a[1..] { t += x; }"), Vec::<&str>::new());
+        assert_eq!(check("for x in [0..10:0] { }"), [codes::INVALID_OPERATION]);
+    }
+
+    #[test]
+    fn writes_through_slices_follow_the_array() {
+        assert_eq!(check("mut a = [1, 2, 3]; let s = a[..]; s[0] = 9; let t: []mut i32 = s[1..]; t[0] += 1;"), Vec::<&str>::new());
+        assert_eq!(check("let a = [1, 2, 3]; let s = a[..]; s[0] = 9;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let a = [1, 2, 3]; mut s = a[..]; s[0]++;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let a = [1, 2, 3]; let s = a[..]; let t = s[1..]; t[0] = 9;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let a = [1, 2, 3]; let s: []mut i32 = a[..];"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("mut a = [1, 2, 3]; let s: []i32 = a[..]; s[0] = 9;"), [codes::IMMUTABLE_ASSIGNMENT]);
+
+        let source = "P -> struct {\n    n i32,\n    bump -> sub(&this) { this.n += 1; }\n}\nclear -> sub(s []mut i32) { s[0] = 0; }\n";
+        let body = |body: &str| check_in(&format!("{}main -> sub() {{\n{}\n}}\n", source, body), "test.ig", None);
+        assert_eq!(body("mut a = [1, 2]; clear(a[..]); mut ps = [P { n: 1 }]; ps[..][0].bump();"), Vec::<&str>::new());
+        assert_eq!(body("let a = [1, 2]; clear(a[..]);"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(body("let ps = [P { n: 1 }]; let s = ps[..]; s[0].bump();"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(body("let ps = [P { n: 1 }]; let s = ps[..]; s[0].n = 2;"), [codes::IMMUTABLE_ASSIGNMENT]);
+    }
+
+    #[test]
+    fn strings_are_read_only() {
+        assert_eq!(check("let s = \"abc\"; let n: usize = s.len; let c: u8 = s[0]; let t: string = s[1..];"), Vec::<&str>::new());
+        assert_eq!(check("let s = \"abc\"; s[0] = 65;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("mut s = \"abc\"; s[0] = 65;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let s = \"abc\"[1..]; s[0]++;"), [codes::IMMUTABLE_ASSIGNMENT]);
+        assert_eq!(check("let s: []mut u8 = \"abc\";"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("mut b: [3]u8 = [97, 98, 99]; b[..][0] = 65; let s: string = b[..];"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn chars() {
+        assert_eq!(check("let c = 'a'; let n = c as u32; let d = 66 as char; let b = c == d || c < 'z';"), Vec::<&str>::new());
+        assert_eq!(check("let s = \"abc\"; let b = s[0] == 'a'; let n: i32 = 'a' + 1;"), Vec::<&str>::new());
+        assert_eq!(check("let b: u8 = '\\u{E9}';"), [codes::LITERAL_OUT_OF_RANGE]);
+        assert_eq!(check("let c: char = 97;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let c = 'a' + 'b';"), [codes::INVALID_OPERATION]);
+        assert_eq!(check("let c = 'a'; let d = c + 1;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let c = 1.5 as char;"), [codes::INVALID_CAST]);
+    }
+
+    #[test]
+    fn number_literals() {
+        assert_eq!(check("let a = 0xFF; let b: i32 = a; let c = 0b1010 + 0o17 + 1_000;"), Vec::<&str>::new());
+        assert_eq!(check("let a = 255u8; let b: u8 = a; let c = 1.5e-9; let d = .5f64; let e: f64 = d;"), Vec::<&str>::new());
+        assert_eq!(check("let a = 3f32; let b: f32 = a;"), Vec::<&str>::new());
+        assert_eq!(check("let a = 300u8;"), [codes::LITERAL_OUT_OF_RANGE]);
+        assert_eq!(check("let a: i32 = 0xFFFF_FFFF;"), [codes::LITERAL_OUT_OF_RANGE]);
+        assert_eq!(check("let a: u8 = 5i32;"), [codes::MISMATCHED_TYPES]);
+        assert_eq!(check("let a: f32 = 1.5f64;"), [codes::MISMATCHED_TYPES]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::lexer::Span;
+
+use crate::ty::Ty;
+
+use super::is_method_symbol;
+
+#[derive(Debug, Clone)]
+pub struct FunctionSig {
+    pub params: Vec<Ty>,
+    pub ret: Ty,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub fields: Vec<(String, Ty)>,
+    pub span: Span,
+}
+
+impl StructDef {
+    pub fn field(&self, name: &str) -> Option<(usize, &Ty)> {
+        self.fields.iter().enumerate().find(|(_, (n, _))| n == name).map(|(i, (_, ty))| (i, ty))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Symbol {
+    Variable { ty: Ty, mutable: bool, span: Span },
+    Function(FunctionSig),
+    Struct(Span),
+}
+
+impl Symbol {
+    pub fn span(&self) -> Span {
+        match self {
+            Symbol::Variable { span, .. } => *span,
+            Symbol::Function(sig) => sig.span,
+            Symbol::Struct(span) => *span,
+        }
+    }
+}
+
+type Frame = HashMap<String, Symbol>;
+
+/// Stack of symbol frames, the first holds symbols imported by includes
+/// and the second the declarations of the module being checked
+pub struct Scope {
+    frames: Vec<Frame>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self {
+            frames: vec![Frame::new(), Frame::new()],
+        }
+    }
+
+    pub fn push(&mut self) {
+        self.frames.push(Frame::new());
+    }
+
+    pub fn pop(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn import(&mut self, name: String, symbol: Symbol) {
+        self.frames[0].insert(name, symbol);
+    }
+
+    pub fn define(&mut self, name: String, symbol: Symbol) {
+        self.frames.last_mut().unwrap().insert(name, symbol);
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<&Symbol> {
+        self.frames.iter().rev().find_map(|f| f.get(name))
+    }
+
+    pub fn resolve_local(&self, name: &str) -> Option<&Symbol> {
+        self.frames.last().unwrap().get(name)
+    }
+
+    /// Declarations made by the module itself, which is what including it exposes. Methods
+    /// it imported go along too, a struct can reach further up than the file that defines it
+    pub fn exports(&self) -> impl Iterator<Item = (&String, &Symbol)> {
+        let methods = self.frames[0].iter().filter(|(name, _)| is_method_symbol(name));
+        self.frames[1].iter().chain(methods)
+    }
+}
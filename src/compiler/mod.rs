@@ -4,10 +4,9 @@ pub mod scope;
 pub mod value;
 pub mod namegen;
 
-use std::{alloc::{self, Layout}, any::Any, collections::HashMap, ffi::{CStr, CString}, path::Path, process::{self, Command}};
+use std::{alloc::{self, Layout}, ffi::CString, path::Path, process::Command};
 
-use llvm_sys_180::{core::{LLVMAddFunction, LLVMAddGlobal, LLVMAppendBasicBlock, LLVMAppendBasicBlockInContext, LLVMBuildAdd, LLVMBuildAlloca, LLVMBuildAnd, LLVMBuildBr, LLVMBuildCall2, LLVMBuildCondBr, LLVMBuildFAdd, LLVMBuildFCmp, LLVMBuildFDiv, LLVMBuildFMul, LLVMBuildFSub, LLVMBuildGEP2, LLVMBuildGlobalString, LLVMBuildICmp, LLVMBuildInsertValue, LLVMBuildLoad2, LLVMBuildMul, LLVMBuildNot, LLVMBuildOr, LLVMBuildPointerCast, LLVMBuildRet, LLVMBuildRetVoid, LLVMBuildSDiv, LLVMBuildStore, LLVMBuildStructGEP2, LLVMBuildSub, LLVMConstInt, LLVMConstReal, LLVMContextCreate, LLVMCreateBuilderInContext, LLVMDoubleTypeInContext, LLVMFloatTypeInContext, LLVMFunctionType, LLVMGetAggregateElement, LLVMGetBasicBlockParent, LLVMGetInsertBlock, LLVMGetParam, LLVMGetReturnType, LLVMGetStructElementTypes, LLVMGetStructName, LLVMGetTypeKind, LLVMGetValueName, LLVMGetValueName2, LLVMHalfTypeInContext, LLVMIntTypeInContext, LLVMModuleCreateWithNameInContext, LLVMPointerType, LLVMPositionBuilderAtEnd, LLVMPrintModuleToFile, LLVMSetInitializer, LLVMStructType, LLVMStructTypeInContext, LLVMTypeOf, LLVMVoidTypeInContext}, prelude::{LLVMBasicBlockRef, LLVMBuilderRef, LLVMContextRef, LLVMModuleRef, LLVMTypeRef, LLVMValueRef}, LLVMContext, LLVMTypeKind, LLVMValue};
-use logos::Logos;
+use llvm_sys_180::{core::{LLVMAddFunction, LLVMAddIncoming, LLVMAddGlobal, LLVMAppendBasicBlockInContext, LLVMArrayType2, LLVMBuildAShr, LLVMBuildAdd, LLVMBuildAlloca, LLVMBuildAnd, LLVMBuildBitCast, LLVMBuildBr, LLVMBuildCall2, LLVMBuildCondBr, LLVMBuildFAdd, LLVMBuildFCmp, LLVMBuildFDiv, LLVMBuildFMul, LLVMBuildFNeg, LLVMBuildFPExt, LLVMBuildFree, LLVMBuildMalloc, LLVMBuildFRem, LLVMBuildFPToSI, LLVMBuildFPToUI, LLVMBuildFPTrunc, LLVMBuildFSub, LLVMBuildExtractValue, LLVMBuildGlobalStringPtr, LLVMBuildInBoundsGEP2, LLVMBuildICmp, LLVMBuildIntToPtr, LLVMBuildInsertValue, LLVMBuildLShr, LLVMBuildLoad2, LLVMBuildMul, LLVMBuildNeg, LLVMBuildNot, LLVMBuildOr, LLVMBuildPhi, LLVMBuildPtrToInt, LLVMBuildRet, LLVMBuildRetVoid, LLVMBuildSDiv, LLVMBuildSRem, LLVMBuildSelect, LLVMBuildShl, LLVMBuildSExt, LLVMBuildSIToFP, LLVMBuildStore, LLVMBuildStructGEP2, LLVMBuildZExt, LLVMBuildSub, LLVMBuildTrunc, LLVMBuildUDiv, LLVMBuildUIToFP, LLVMBuildURem, LLVMBuildXor, LLVMBuildUnreachable, LLVMConstAllOnes, LLVMConstInt, LLVMConstReal, LLVMConstStringInContext, LLVMContextCreate, LLVMCreateBuilderInContext, LLVMDoubleTypeInContext, LLVMFloatTypeInContext, LLVMFunctionType, LLVMGetBasicBlockParent, LLVMGetBasicBlockTerminator, LLVMGetEntryBasicBlock, LLVMGetFirstInstruction, LLVMGetInsertBlock, LLVMGetIntTypeWidth, LLVMGetUndef, LLVMGetParam, LLVMGetReturnType, LLVMGetNamedFunction, LLVMGetTypeKind, LLVMGlobalGetValueType, LLVMHalfTypeInContext, LLVMIntTypeInContext, LLVMModuleCreateWithNameInContext, LLVMPointerType, LLVMPositionBuilderAtEnd, LLVMPositionBuilderBefore, LLVMPrintModuleToFile, LLVMSetGlobalConstant, LLVMSetInitializer, LLVMSetLinkage, LLVMIsOpaqueStruct, LLVMStructCreateNamed, LLVMStructSetBody, LLVMStructTypeInContext, LLVMTypeOf, LLVMVoidTypeInContext}, prelude::{LLVMBasicBlockRef, LLVMBuilderRef, LLVMContextRef, LLVMModuleRef, LLVMTypeRef, LLVMValueRef}, LLVMContext, LLVMLinkage, LLVMTypeKind};
 use namegen::{gen_id, gen_id_pre, gen_id_prepost};
 use scope::IGScope;
 use value::IGValue;
@@ -15,266 +14,586 @@ use value::IGValue;
 use llvm_sys_180::LLVMRealPredicate as FPredicate;
 use llvm_sys_180::LLVMIntPredicate as IPredicate;
 
-use crate::{lexer::{self, Token}, parser::{ast::{Expr, Stmt, Type}, is_kind, Parser}};
+use crate::{checker::method_symbol, diagnostics::{codes, Diagnostic, Diagnostics}, lexer::{Span, Token}, parser::ast::{Expr, ExprKind, Stmt, StmtKind, Type}, ty::Ty};
 
-type TypeMap = HashMap<String, LLVMTypeRef>;
+pub type CompileResult<T> = Result<T, Diagnostic>;
 
 struct IGLib {
     lib: String,
     _static: bool,
 }
 
-pub struct Compiler{
-    type_map: TypeMap,
-    libs: Vec<IGLib>,
+struct IGLoop {
+    label: Option<String>,
 
-    include_paths: Vec<String>,
+    /// Where `continue` jumps to
+    next: LLVMBasicBlockRef,
+    exit: LLVMBasicBlockRef,
+}
+
+pub struct Compiler<'a> {
+    libs: Vec<IGLib>,
 
     context: LLVMContextRef,
     module: LLVMModuleRef,
     builder: LLVMBuilderRef,
 
+    /// Places stack slots at the top of the function, away from where `builder` is
+    alloca_builder: LLVMBuilderRef,
+
     current_scope: IGScope,
+    loops: Vec<IGLoop>,
+    diagnostics: &'a mut Diagnostics,
 
     output: String,
     outputs: Vec<String>,
-    cwd: String,
-}
-
-impl Compiler {
-    unsafe fn get_type_map(context: *mut LLVMContext) -> TypeMap {
-        let mut type_map = TypeMap::new();
-
-        type_map.insert("bool".into(), LLVMIntTypeInContext(context,1));
-        type_map.insert("i8".into(), LLVMIntTypeInContext(context,8));
-        type_map.insert("i16".into(), LLVMIntTypeInContext(context, 16));
-        type_map.insert("i32".into(), LLVMIntTypeInContext(context, 32));
-        type_map.insert("i64".into(), LLVMIntTypeInContext(context, 64));
-
-        type_map.insert("f16".into(), LLVMHalfTypeInContext(context));
-        type_map.insert("f32".into(), LLVMFloatTypeInContext(context));
-        type_map.insert("f64".into(), LLVMDoubleTypeInContext(context));
-        
-        type_map.insert("bool".into(), LLVMIntTypeInContext(context, 1));
-        type_map.insert("string".into(), LLVMPointerType(LLVMIntTypeInContext(context, 8), 0));
-        type_map.insert("void".into(), LLVMVoidTypeInContext(context));
 
-        type_map
-    }
+    /// Whether overflow, division by zero and out of bounds indexing panic at runtime
+    checks: bool,
+}
 
-    unsafe fn get_type(&self, _type: Type) -> LLVMTypeRef {
-        if let Type::Symbol(t) = _type {
-            if let Some(t) = self.type_map.get(&t) {
-                t.clone()
-            } else if let Some(t) = self.current_scope.resolve(t.clone()) {
-                t._type
-            } else {
-                panic!("Invalid type {:?}", t);
-            }
-        } else if let Type::Ref(t) = _type {
-            self.get_type(*t)
-        } else {
-            panic!("No support for type {:?}", _type);
+impl<'a> Compiler<'a> {
+    unsafe fn llvm_type(&self, ty: &Ty) -> LLVMTypeRef {
+        match ty {
+            Ty::Void => LLVMVoidTypeInContext(self.context),
+            Ty::Bool => LLVMIntTypeInContext(self.context, 1),
+            Ty::Char => LLVMIntTypeInContext(self.context, 32),
+            Ty::Int { bits, .. } => LLVMIntTypeInContext(self.context, *bits),
+            Ty::Float { bits: 16 } => LLVMHalfTypeInContext(self.context),
+            Ty::Float { bits: 32 } => LLVMFloatTypeInContext(self.context),
+            Ty::Float { .. } => LLVMDoubleTypeInContext(self.context),
+            Ty::Ref(inner) => LLVMPointerType(self.llvm_type(inner), 0),
+            Ty::Struct(name) => self.current_scope.resolve(name.clone()).expect("Struct type was not declared")._type,
+            Ty::Array { len, elem } => LLVMArrayType2(self.llvm_type(elem), *len as u64),
+            Ty::Slice { elem, .. } => {
+                let mut fields = [LLVMPointerType(self.llvm_type(elem), 0), self.llvm_type(&Ty::from_name("usize").unwrap())];
+                LLVMStructTypeInContext(self.context, fields.as_mut_ptr(), fields.len() as u32, 0)
+            },
+            Ty::Error => panic!("Type error reached code generation"),
         }
     }
 
-    unsafe fn get_type_by_name(&self, name: &str) -> LLVMTypeRef {
-        self.get_type(Type::Symbol(name.into()))
+    unsafe fn get_type(&self, _type: &Type) -> LLVMTypeRef {
+        self.llvm_type(_type.ty())
     }
 
-    unsafe fn new(output: String, include_paths: Vec<String>, cwd: Option<String>) -> Self {
+    unsafe fn new(output: String, checks: bool, diagnostics: &'a mut Diagnostics) -> Self {
         let context: *mut LLVMContext = LLVMContextCreate();
-        let _cwd = if let Some(c) = cwd {
-            c
-        } else {
-            std::env::current_dir().unwrap().to_string_lossy().to_string()
-        };
 
         Self {
-            type_map: Self::get_type_map(context),
-            
             libs: vec![],
 
-            include_paths,
             outputs: vec![Path::new(&output.clone()).with_extension("ll").to_string_lossy().to_string()],
 
-            current_scope: IGScope::new(None, None, None, None),
+            current_scope: IGScope::new(),
+            loops: vec![],
+            diagnostics,
 
             module: LLVMModuleCreateWithNameInContext(get_cstring("ignis".into()), context),
             builder: LLVMCreateBuilderInContext(context),
+            alloca_builder: LLVMCreateBuilderInContext(context),
             context,
             output,
-            cwd: _cwd,
+            checks,
         }
     }  
 
-    fn execute_command(cmd: &str, args: Vec<&str>) {
+    fn execute_command(cmd: &str, args: Vec<&str>) -> CompileResult<()> {
         println!("executing: {} {:?}", cmd, args);
 
         let out = Command::new(cmd)
             .args(args)
             .output()
-            .expect(&format!("Failed to execute ''{}'", cmd));
-        if !out.status.success() {
-            println!("'{}' executed with code {:?}", cmd, out.status.code().unwrap());
-        }
+            .map_err(|e| Diagnostic::error(codes::TOOLCHAIN_FAILED, format!("failed to execute `{}`: {}", cmd, e)))?;
+
         let stdout = String::from_utf8_lossy(&out.stdout);
         let stderr = String::from_utf8_lossy(&out.stderr);
         if !stdout.is_empty() {
             println!("{}", stdout);
         } 
+
+        if !out.status.success() {
+            let mut error = Diagnostic::error(codes::TOOLCHAIN_FAILED, format!("`{}` failed with {}", cmd, out.status));
+            for line in stderr.lines().filter(|l| !l.trim().is_empty()) {
+                error = error.with_note(line.to_string());
+            }
+            return Err(error);
+        }
+
         if !stderr.is_empty() {
             println!("{}", stderr);
         }
+        Ok(())
     }
 
-    pub fn compile(output: &Path, ast: Stmt, include_paths: Vec<String>, cwd: Option<String>, inside: bool) -> Self {
+    /// Compiles the type checked `ast` into `output`, reporting any errors into `diagnostics`.
+    /// `checks` adds the runtime checks that release builds leave out
+    pub fn compile(output: &Path, ast: Stmt, inside: bool, checks: bool, diagnostics: &'a mut Diagnostics) -> Self {
         unsafe {
-            let mut compiler = Self::new(output.to_string_lossy().to_string(), include_paths, cwd);
-            compiler.visit_block(ast);
+            let mut compiler = Self::new(output.to_string_lossy().to_string(), checks, diagnostics);
+            compiler.declare_module(&ast, 0);
+            if let Err(e) = compiler.visit_block(ast) {
+                compiler.diagnostics.report(e);
+            }
+
+            if compiler.diagnostics.has_errors() {
+                return compiler;
+            }
+
             compiler.write_ir(&output.with_extension("ll"));
 
-            if !inside {
-                let mut obj_files = vec![];
-                for out in compiler.outputs.iter() {
-                    let path = Path::new(&out.clone()).with_extension("o");
-                    obj_files.push(path.to_string_lossy().to_string());
-                    Self::execute_command("llc", vec!["--filetype=obj", &out, "-o", path.to_str().unwrap()]);
-                }
-                
-                let mut comp_arg = vec!["-o", output.to_str().unwrap(), "-no-pie"];
+            if !inside && let Err(e) = compiler.link(output) {
+                compiler.diagnostics.report(e);
+            }
 
-                for obj in &obj_files {
-                    comp_arg.push(obj);
-                }
+            compiler
+        }
+    }
 
-                let mut libs = vec![];
+    fn link(&self, output: &Path) -> CompileResult<()> {
+        let mut obj_files = vec![];
+        for out in self.outputs.iter() {
+            let path = Path::new(&out.clone()).with_extension("o");
+            obj_files.push(path.to_string_lossy().to_string());
+            Self::execute_command("llc", vec!["--filetype=obj", &out, "-o", path.to_str().unwrap()])?;
+        }
+        
+        let mut comp_arg = vec!["-o", output.to_str().unwrap(), "-no-pie"];
 
-                for lib in compiler.libs.iter() {
-                    libs.push(if lib._static {
-                        lib.lib.clone()
-                    } else {
-                        ["-l", &lib.lib].join("")
-                    });
-                }
-                
-                for lib in libs.iter() {
-                    comp_arg.push(lib);
-                }
+        for obj in &obj_files {
+            comp_arg.push(obj);
+        }
 
-                Self::execute_command("gcc", comp_arg);
+        let mut libs = vec![];
 
-                for obj in &obj_files {
-                    std::fs::remove_file(obj).expect("Failed to remove obj files");
-                }
+        for lib in self.libs.iter() {
+            libs.push(if lib._static {
+                lib.lib.clone()
+            } else {
+                ["-l", &lib.lib].join("")
+            });
+        }
+        
+        for lib in libs.iter() {
+            comp_arg.push(lib);
+        }
 
-                for out in compiler.outputs.clone() {
-                    // std::fs::remove_file(out).expect("Failed to remove llvm ir files");
-                }
-            }
+        // Float exponentiation lowers to `pow` from the C math library
+        comp_arg.push("-lm");
 
-            compiler
+        let linked = Self::execute_command("gcc", comp_arg);
 
+        for obj in &obj_files {
+            let _ = std::fs::remove_file(obj);
         }
+
+        linked
     }
 
-    unsafe fn visit_block(&mut self, stmt: Stmt) {
-        if let Stmt::Block(block) = stmt {
+    unsafe fn visit_block(&mut self, stmt: Stmt) -> CompileResult<()> {
+        if let StmtKind::Block(block) = stmt.kind {
+            self.current_scope.push();
             for s in block {
-                self.visit(s);
+                if let Err(e) = self.visit(s) {
+                    self.current_scope.pop();
+                    return Err(e);
+                }
             }
+            self.current_scope.pop();
+            Ok(())
         } else {
             panic!("Expected block");
         }
     }
 
-    unsafe fn visit(&mut self, stmt: Stmt) {
-        if let Stmt::Expression(expr) = stmt {
-            self.visit_expression(*expr);
-        } else if let Stmt::FunctionDeclaration { .. } = stmt {
-            self.visit_function_declaration(stmt.clone());
-        } else if let Stmt::Return { .. } = stmt {
-            self.visit_return(stmt.clone());
-        } else if let Stmt::VariableDeclaration { .. } = stmt {
-            self.visit_variable_declaration(stmt.clone(), true);
-        } else if let Stmt::Extern { .. } = stmt {
-            self.visit_extern(stmt.clone());   
-        } else if let Stmt::Include { .. } = stmt {
-            self.visit_include(stmt.clone());
-        } else if let Stmt::Link { library, _static } = stmt {
-            self.libs.push(IGLib { lib: library, _static: _static })
-        } else if let Stmt::While { .. } = stmt {
-            self.visit_while(stmt.clone());  
-        } else if let Stmt::StructDeclaration { .. } = stmt {
-            self.visit_struct_declaration(stmt.clone());  
+    unsafe fn visit(&mut self, stmt: Stmt) -> CompileResult<()> {
+        if let StmtKind::Expression(expr) = stmt.kind {
+            self.visit_expression(*expr)
+        } else if let StmtKind::FunctionDeclaration { .. } = stmt.kind {
+            self.visit_function_declaration(stmt.clone())
+        } else if let StmtKind::Return { .. } = stmt.kind {
+            self.visit_return(stmt.clone())
+        } else if let StmtKind::Delete { value } = stmt.kind {
+            let ptr = self.resolve_value(*value)?;
+            LLVMBuildFree(self.builder, ptr.value);
+            Ok(())
+        } else if let StmtKind::VariableDeclaration { .. } = stmt.kind {
+            self.visit_variable_declaration(stmt.clone())
+        } else if let StmtKind::Extern { .. } = stmt.kind {
+            // Declared up front by `declare_module`
+            Ok(())
+        } else if let StmtKind::Include { .. } = stmt.kind {
+            self.visit_include(stmt.clone())
+        } else if let StmtKind::Link { library, _static } = stmt.kind {
+            self.libs.push(IGLib { lib: library, _static });
+            Ok(())
+        } else if let StmtKind::While { .. } = stmt.kind {
+            self.visit_while(stmt.clone())
+        } else if let StmtKind::For { .. } = stmt.kind {
+            self.visit_for(stmt.clone())
+        } else if let StmtKind::If { .. } = stmt.kind {
+            self.visit_if(stmt.clone())
+        } else if let StmtKind::Break { .. } | StmtKind::Continue { .. } = stmt.kind {
+            self.visit_loop_control(stmt.clone());
+            Ok(())
+        } else if let StmtKind::StructDeclaration { .. } = stmt.kind {
+            self.visit_struct_declaration(stmt.clone())
         } else {
-            panic!("Unsupported statement: {:?}", stmt);
+            Err(Diagnostic::error(codes::UNSUPPORTED, "this statement is not supported yet")
+                .with_label(stmt.span, "unsupported statement"))
         }
     }
 
-    unsafe fn visit_expression(&mut self, expr: Expr) {
-        if let Expr::Binary { .. } = expr {
-            self.visit_binexpr(expr);
-        } else if let Expr::Assignment { .. } = expr {
-            self.visit_assignment_expr(expr);
-        } else if let Expr::Call { .. } = expr {
-            self.visit_call_expr(expr);
+    unsafe fn visit_expression(&mut self, expr: Expr) -> CompileResult<()> {
+        if let ExprKind::Binary { .. } = expr.kind {
+            self.visit_binexpr(expr)?;
+        } else if let ExprKind::Assignment { .. } = expr.kind {
+            self.visit_assignment_expr(expr)?;
+        } else if let ExprKind::CompoundAssignment { .. } = expr.kind {
+            self.visit_compound_assignment(expr)?;
+        } else if let ExprKind::Increment { .. } = expr.kind {
+            self.visit_increment(expr)?;
+        } else if let ExprKind::Call { .. } | ExprKind::MethodCall { .. } = expr.kind {
+            self.visit_call_expr(expr)?;
         } else {
-            panic!("Unsupported expression: {:?}", expr);
+            return Err(Diagnostic::error(codes::UNSUPPORTED, "this expression is not supported as a statement")
+                .with_label(expr.span, "unsupported expression"));
         }
+        Ok(())
     }
 
-    unsafe fn visit_struct_declaration(&mut self, stmt: Stmt) {
-        let Stmt::StructDeclaration { name, fields, functions } = stmt else {
+    unsafe fn visit_struct_declaration(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::StructDeclaration { name, functions, .. } = stmt.kind else {
             panic!("Expected struct declaration");
         };
 
-        let field_types = self.get_arg_types(fields.clone());
-        let _type = LLVMStructTypeInContext(self.context,field_types.clone().as_mut_ptr(), field_types.len() as u32, 0);
+        for mut function in functions {
+            let StmtKind::FunctionDeclaration { name: method, .. } = &mut function.kind else {
+                panic!("Expected function declaration");
+            };
+
+            *method = method_symbol(&name, method);
+            self.visit_function_declaration(function)?;
+        }
+        Ok(())
+    }
+
+    /// Declares the structs, functions and externs of a module before anything is compiled, so they can
+    /// be used ahead of where they are written. Included modules are compiled separately and linked in,
+    /// their structs and methods are needed from every module down the include chain but functions only from direct ones
+    unsafe fn declare_module(&mut self, ast: &Stmt, depth: usize) {
+        let StmtKind::Block(body) = &ast.kind else {
+            panic!("Expected block");
+        };
+
+        // Opaque types first so struct bodies can refer to each other
+        for stmt in body {
+            if let StmtKind::StructDeclaration { name, .. } = &stmt.kind {
+                self.declare_struct_type(name);
+            }
+        }
+
+        for stmt in body {
+            if let StmtKind::Include { module: Some(module), .. } = &stmt.kind {
+                self.declare_module(&module.ast, depth + 1);
+            }
+        }
+
+        for stmt in body {
+            if let StmtKind::StructDeclaration { name, fields, .. } = &stmt.kind {
+                self.define_struct_body(name, fields);
+            }
+        }
+
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::StructDeclaration { name, functions, .. } => {
+                    for function in functions {
+                        let StmtKind::FunctionDeclaration { name: method, return_type, arguments, .. } = &function.kind else {
+                            panic!("Expected function declaration");
+                        };
+
+                        let symbol = method_symbol(name, method);
+                        self.declare_function(symbol.clone(), symbol, return_type, arguments, false);
+                    }
+                },
+                StmtKind::FunctionDeclaration { name, return_type, arguments, .. } if depth <= 1 => {
+                    self.declare_function(name.clone(), name.clone(), return_type, arguments, name == "main");
+                },
+                StmtKind::Extern { name, symbol, return_type, arguments } if depth <= 1 => {
+                    self.declare_function(name.clone(), symbol.clone(), return_type, arguments, false);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    unsafe fn declare_struct_type(&mut self, name: &str) {
+        // A struct can reach here through more than one include
+        if self.current_scope.resolve(name.into()).is_some() {
+            return;
+        }
+
+        let _type = LLVMStructCreateNamed(self.context, get_cstring(name.into()));
+        self.current_scope.define_type(name.into(), _type);
+    }
+
+    unsafe fn define_struct_body(&mut self, name: &str, fields: &[Stmt]) {
+        let _type = self.llvm_type(&Ty::Struct(name.into()));
+        if LLVMIsOpaqueStruct(_type) == 0 {
+            return;
+        }
 
-        self.current_scope.define_type(name.clone(), _type, false, true);
+        let mut field_types = self.get_arg_types(fields);
+        LLVMStructSetBody(_type, field_types.as_mut_ptr(), field_types.len() as u32, 0);
 
         for (i, field_stmt) in fields.iter().enumerate() {
-            let Stmt::Field { name: _name, _type } = field_stmt else {
+            let StmtKind::Field { name: _name, .. } = &field_stmt.kind else {
                 panic!("Expected field");
             };
-            let mut newname= name.clone();
-            newname.push_str(".");
-            newname.push_str(&_name);
 
-
-            self.current_scope.define_field(newname, i);
+            self.current_scope.define_field(format!("{}.{}", name, _name), i);
         }
     }
 
-    unsafe fn visit_extern(&mut self, stmt: Stmt) {
-        let Stmt::Extern { name, symbol, return_type, arguments } = stmt else {
-            panic!("Expected extern");
-        };
+    unsafe fn declare_function(&mut self, name: String, symbol: String, return_type: &Type, arguments: &[Stmt], main: bool) {
+        let func_type = self.get_function_type(return_type, arguments, main);
+        let func = self.get_or_add_function(symbol, func_type);
+        self.current_scope.define(name, func, func_type);
+    }
 
-        let types = self.get_arg_types(arguments.clone());
-        let ret_type = self.get_type(*return_type);
-        let func_type = LLVMFunctionType(ret_type, types.clone().as_mut_ptr(), types.len() as u32, 0);
-        let func = LLVMAddFunction(self.module, get_cstring(symbol), func_type);
+    unsafe fn get_function_type(&self, return_type: &Type, arguments: &[Stmt], main: bool) -> LLVMTypeRef {
+        let mut arg_types = self.get_arg_types(arguments);
+        let mut ret_type = self.get_type(return_type);
+
+        if main && return_type.ty().is_void() {
+            ret_type = self.llvm_type(&Ty::Int { bits: 32, signed: true });
+        }
 
-        self.current_scope.define(name, func, func_type, false, true);
+        LLVMFunctionType(ret_type, arg_types.as_mut_ptr(), arg_types.len() as u32, 0)
     }
 
-    unsafe fn visit_while(&mut self, stmt: Stmt) {
-        let Stmt::While { condition, body } = stmt else {
+    unsafe fn get_or_add_function(&mut self, symbol: String, func_type: LLVMTypeRef) -> LLVMValueRef {
+        let name = get_cstring(symbol);
+        let func = LLVMGetNamedFunction(self.module, name);
+        if func.is_null() {
+            LLVMAddFunction(self.module, name, func_type)
+        } else {
+            func
+        }
+    }
+
+    unsafe fn visit_while(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::While { condition, body, label } = stmt.kind else {
             panic!("Expected while statement");
         };
 
-        let thenbb = self.create_basic_block("while_inside".into());
-        let outisdebb = self.create_basic_block("while_outside".into());
+        let headerbb = self.create_basic_block("while_header".into());
+        let bodybb = self.create_basic_block("while_body".into());
+        let exitbb = self.create_basic_block("while_exit".into());
     
-        LLVMBuildBr(self.builder, thenbb);
+        LLVMBuildBr(self.builder, headerbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, headerbb);
+        let cond = self.visit_conditional(*condition)?;
+        LLVMBuildCondBr(self.builder, cond.value, bodybb, exitbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, bodybb);
+        self.loops.push(IGLoop { label, next: headerbb, exit: exitbb });
+        let visited = self.visit_block(*body);
+        self.loops.pop();
+        visited?;
+        self.branch_if_open(headerbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, exitbb);
+        Ok(())
+    }
+
+    unsafe fn visit_for(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::For { variable, iterable, body, label } = stmt.kind else {
+            panic!("Expected for statement");
+        };
+
+        if let ExprKind::Range { .. } = iterable.kind {
+            self.visit_range_for(variable, *iterable, *body, label)
+        } else {
+            self.visit_elements_for(variable, *iterable, *body, label)
+        }
+    }
+
+    /// Counts from the start up to the end, a step at a time
+    unsafe fn visit_range_for(&mut self, variable: String, range: Expr, body: Stmt, label: Option<String>) -> CompileResult<()> {
+        let Ty::Int { signed, .. } = range.ty().clone() else {
+            panic!("Expected integer range");
+        };
+        let ExprKind::Range { start, end, inclusive, step } = range.kind else {
+            panic!("Expected range");
+        };
+
+        let _type = self.llvm_type(start.ty());
+
+        // Bounds and step are evaluated once, before the first iteration
+        let start = self.resolve_value(*start)?;
+        let end = self.resolve_value(*end)?;
+        let step = if let Some(step) = step {
+            let span = step.span;
+            let step = self.resolve_value(*step)?.value;
+            let predicate = if signed { IPredicate::LLVMIntSGT } else { IPredicate::LLVMIntNE };
+            let positive = LLVMBuildICmp(self.builder, predicate, step, LLVMConstInt(_type, 0, 0), gen_id());
+            self.build_check(positive, span, "range step must be positive", &[]);
+            step
+        } else {
+            LLVMConstInt(_type, 1, 0)
+        };
+
+        let counter = self.build_alloca(_type, gen_id_pre(variable.clone()));
+        LLVMBuildStore(self.builder, start.value, counter);
+
+        let bodybb = self.create_basic_block("for_body".into());
+        let latchbb = self.create_basic_block("for_latch".into());
+        let stepbb = self.create_basic_block("for_step".into());
+        let exitbb = self.create_basic_block("for_exit".into());
+
+        let predicate = match (inclusive, signed) {
+            (false, true) => IPredicate::LLVMIntSLT,
+            (true, true) => IPredicate::LLVMIntSLE,
+            (false, false) => IPredicate::LLVMIntULT,
+            (true, false) => IPredicate::LLVMIntULE,
+        };
+        let cond = LLVMBuildICmp(self.builder, predicate, start.value, end.value, gen_id());
+        LLVMBuildCondBr(self.builder, cond, bodybb, exitbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, bodybb);
+        self.visit_loop_body(variable, counter, _type, body, IGLoop { label, next: latchbb, exit: exitbb })?;
+
+        // The loop exits before a step that would go past the end, so the counter never
+        // overflows even when the end is the largest value of its type. The counter hasn't
+        // passed the end yet, so the distance to it is exact as an unsigned number
+        LLVMPositionBuilderAtEnd(self.builder, latchbb);
+        let current = LLVMBuildLoad2(self.builder, _type, counter, gen_id());
+        let remaining = LLVMBuildSub(self.builder, end.value, current, gen_id());
+        let predicate = if inclusive { IPredicate::LLVMIntUGT } else { IPredicate::LLVMIntUGE };
+        let last = LLVMBuildICmp(self.builder, predicate, step, remaining, gen_id());
+        LLVMBuildCondBr(self.builder, last, exitbb, stepbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, stepbb);
+        let next = LLVMBuildAdd(self.builder, current, step, gen_id());
+        LLVMBuildStore(self.builder, next, counter);
+        LLVMBuildBr(self.builder, bodybb);
+
+        LLVMPositionBuilderAtEnd(self.builder, exitbb);
+        Ok(())
+    }
+
+    /// Gives the loop variable a copy of each element of an array or slice in turn
+    unsafe fn visit_elements_for(&mut self, variable: String, iterable: Expr, body: Stmt, label: Option<String>) -> CompileResult<()> {
+        let elem_type = self.llvm_type(iterable.ty().element().expect("Expected array or slice"));
+        let usize = self.llvm_type(&Ty::from_name("usize").unwrap());
+
+        let (ptr, len) = self.resolve_elements(iterable)?;
+        let index = self.build_alloca(usize, gen_id_pre("index".into()));
+        let element = self.build_alloca(elem_type, gen_id_pre(variable.clone()));
+        let zero = LLVMConstInt(usize, 0, 0);
+        LLVMBuildStore(self.builder, zero, index);
+
+        let bodybb = self.create_basic_block("for_body".into());
+        let latchbb = self.create_basic_block("for_latch".into());
+        let exitbb = self.create_basic_block("for_exit".into());
+
+        let cond = LLVMBuildICmp(self.builder, IPredicate::LLVMIntULT, zero, len, gen_id());
+        LLVMBuildCondBr(self.builder, cond, bodybb, exitbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, bodybb);
+        let mut indices = [LLVMBuildLoad2(self.builder, usize, index, gen_id())];
+        let ptr = LLVMBuildInBoundsGEP2(self.builder, elem_type, ptr, indices.as_mut_ptr(), 1, gen_id());
+        LLVMBuildStore(self.builder, LLVMBuildLoad2(self.builder, elem_type, ptr, gen_id()), element);
+        self.visit_loop_body(variable, element, elem_type, body, IGLoop { label, next: latchbb, exit: exitbb })?;
+
+        LLVMPositionBuilderAtEnd(self.builder, latchbb);
+        let next = LLVMBuildAdd(self.builder, LLVMBuildLoad2(self.builder, usize, index, gen_id()), LLVMConstInt(usize, 1, 0), gen_id());
+        LLVMBuildStore(self.builder, next, index);
+        let cond = LLVMBuildICmp(self.builder, IPredicate::LLVMIntULT, next, len, gen_id());
+        LLVMBuildCondBr(self.builder, cond, bodybb, exitbb);
+
+        LLVMPositionBuilderAtEnd(self.builder, exitbb);
+        Ok(())
+    }
+
+    /// Visits the body of a `for` with `variable` stored at `slot`, ending with a jump to where `continue` goes
+    unsafe fn visit_loop_body(&mut self, variable: String, slot: LLVMValueRef, _type: LLVMTypeRef, body: Stmt, target: IGLoop) -> CompileResult<()> {
+        let next = target.next;
+        self.current_scope.push();
+        self.current_scope.define(variable, slot, _type);
+        self.loops.push(target);
+        let visited = self.visit_block(body);
+        self.loops.pop();
+        self.current_scope.pop();
+        visited?;
+        self.branch_if_open(next);
+        Ok(())
+    }
+
+    unsafe fn visit_loop_control(&mut self, stmt: Stmt) {
+        let (label, continuing) = match stmt.kind {
+            StmtKind::Break { label } => (label, false),
+            StmtKind::Continue { label } => (label, true),
+            _ => panic!("Expected break or continue"),
+        };
+
+        let target = self.loops.iter().rev()
+            .find(|l| label.is_none() || l.label == label)
+            .expect("Loop control was not checked");
+
+        LLVMBuildBr(self.builder, if continuing { target.next } else { target.exit });
+        self.start_dead_block(if continuing { "after_continue" } else { "after_break" });
+    }
+
+    unsafe fn visit_if(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::If { condition, body, _else } = stmt.kind else {
+            panic!("Expected if statement");
+        };
+
+        let cond = self.visit_conditional(*condition)?;
+
+        let thenbb = self.create_basic_block("if_then".into());
+        let elsebb = if _else.is_some() { Some(self.create_basic_block("if_else".into())) } else { None };
+        let mergebb = self.create_basic_block("if_merge".into());
+
+        LLVMBuildCondBr(self.builder, cond.value, thenbb, elsebb.unwrap_or(mergebb));
 
         LLVMPositionBuilderAtEnd(self.builder, thenbb);
-        self.visit_block(*body);
-        let cond = self.visit_conditional(*condition);
-        LLVMBuildCondBr(self.builder, cond.value, thenbb, outisdebb);
-        LLVMPositionBuilderAtEnd(self.builder, outisdebb);
+        self.visit_block(*body)?;
+        self.branch_if_open(mergebb);
+
+        if let (Some(_else), Some(elsebb)) = (_else, elsebb) {
+            LLVMPositionBuilderAtEnd(self.builder, elsebb);
+            if let StmtKind::If { .. } = _else.kind {
+                self.visit_if(*_else)?;
+            } else {
+                self.visit_block(*_else)?;
+            }
+            self.branch_if_open(mergebb);
+        }
+
+        LLVMPositionBuilderAtEnd(self.builder, mergebb);
+        Ok(())
+    }
+
+    /// Whether the block being built already ends in a branch or return
+    unsafe fn is_terminated(&self) -> bool {
+        !LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null()
+    }
+
+    /// Moves the builder past a terminator, anything emitted after it is unreachable
+    unsafe fn start_dead_block(&mut self, name: &str) {
+        let deadbb = self.create_basic_block(name.into());
+        LLVMPositionBuilderAtEnd(self.builder, deadbb);
+    }
+
+    unsafe fn branch_if_open(&mut self, target: LLVMBasicBlockRef) {
+        if !self.is_terminated() {
+            LLVMBuildBr(self.builder, target);
+        }
     }
 
     unsafe fn create_basic_block(&mut self, name: String) -> LLVMBasicBlockRef {
@@ -287,390 +606,868 @@ impl Compiler {
         LLVMGetBasicBlockParent(bl)
     }
 
-    unsafe fn visit_conditional(&mut self, expr: Expr) -> IGValue {
-        if let Expr::Binary { .. } = expr {
-            self.visit_binexpr(expr)
-        } else if let Expr::Bool(_) = expr {
-            self.resolve_value(expr)
-        } else if let Expr::Prefix { .. } = expr {
-            self.visit_prefix(expr)
+    /// Stack slot in the entry block of the current function, one made inside a loop body
+    /// would otherwise take more of the stack on every iteration
+    unsafe fn build_alloca(&mut self, _type: LLVMTypeRef, name: *mut i8) -> LLVMValueRef {
+        let entry = LLVMGetEntryBasicBlock(self.get_current_function());
+        let first = LLVMGetFirstInstruction(entry);
+        if first.is_null() {
+            LLVMPositionBuilderAtEnd(self.alloca_builder, entry);
         } else {
-            panic!("Expected conditional statement, got {:?}", expr);
+            LLVMPositionBuilderBefore(self.alloca_builder, first);
         }
+        LLVMBuildAlloca(self.alloca_builder, _type, name)
     }
 
-    fn get_include_path(&self, inc: String) -> String {
-        if std::fs::exists(inc.clone()).unwrap() {
-            return inc;
-        }
-
-        let cwd_path = Path::new(&self.cwd).join(inc.clone());
-        if std::fs::exists(cwd_path.clone()).unwrap() {
-            return cwd_path.to_string_lossy().to_string();
-        }
-
-        for path in &self.include_paths {
-            let inc_path = Path::new(path).join(inc.clone());
-            if std::fs::exists(inc_path.clone()).unwrap() {
-                return inc_path.to_string_lossy().to_string();
-            }
-        }
-
-        panic!("Failed to include source file {:?}", inc);
+    unsafe fn visit_conditional(&mut self, expr: Expr) -> CompileResult<IGValue> {
+        // The checker already made sure conditions are `bool`
+        self.resolve_value(expr)
     }
 
-    unsafe fn visit_prefix(&mut self, expr: Expr) -> IGValue {
-        let Expr::Prefix { op, right } = expr else {
+    unsafe fn visit_prefix(&mut self, expr: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(expr.ty());
+        let floating = expr.ty().is_float();
+        let ExprKind::Prefix { op, right } = expr.kind else {
             panic!("Expected prefix expression");
         };
 
-        let rhs = self.resolve_value(*right);
+        let rhs = self.resolve_value(*right)?;
 
         let val = match op {
-            Token::Not => LLVMBuildNot(self.builder, rhs.value, gen_id()),
-            _ => panic!("Unsupported prefix expression: {:?}", op)
+            Token::Not | Token::BitNot => LLVMBuildNot(self.builder, rhs.value, gen_id()),
+            Token::Minus if floating => LLVMBuildFNeg(self.builder, rhs.value, gen_id()),
+            Token::Minus if self.checks => self.build_overflow_checked("ssub", LLVMConstInt(_type, 0, 0), rhs.value, "negate", expr.span),
+            Token::Minus => LLVMBuildNeg(self.builder, rhs.value, gen_id()),
+            _ => return Err(Diagnostic::error(codes::UNSUPPORTED, format!("prefix operator {} is not supported yet", op))
+                .with_label(expr.span, "unsupported operator")),
         };
 
-        IGValue::new(val, self.get_type_by_name("bool"))
+        Ok(IGValue::new(val, _type))
     }
 
-    unsafe fn visit_include(&mut self, stmt: Stmt) {
-        let Stmt::Include { path } = stmt else {
+    unsafe fn visit_include(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::Include { path, module } = stmt.kind else {
             panic!("Expected include");
         };
 
-        let inc = self.get_include_path(path);
-        let inc_path = Path::new(&inc);
-
-        let outpath = Path::new(&self.output).parent().unwrap();
-        let source = std::fs::read_to_string(inc_path).expect("Failed to include file<NOT FOUND>");
-        let lexer = lexer::Token::lexer(&source);
-        let mut tokens = vec![];
-
-        for t in lexer {
-            match t {
-                Ok(tok) => tokens.push(tok),
-                Err(e) => {
-                    if !e.is_empty() {
-                        println!("Invalid token: {}", e);
-                        return;
-                    }
-                },
-            }
+        let module = module.expect("Include was not resolved by the checker");
+        if !module.compile {
+            return Ok(());
         }
 
-        tokens.push(Token::EOF);
-
-        let ast = Parser::parse(tokens);
+        let inc_path = Path::new(&module.path);
+        let outpath = Path::new(&self.output).parent().unwrap();
         let partial = outpath.join(inc_path.to_string_lossy().to_string().replace("\\", "_").replace("/", "_"));
-        let mut compiler = Self::compile(Path::new(&partial), ast, self.include_paths.clone(), Some(inc_path.parent().unwrap().to_string_lossy().to_string()), true);
-        for (name, value) in compiler.current_scope.symbols {
-            let tk = LLVMGetTypeKind(LLVMTypeOf(value.clone().value));
-            if tk == LLVMTypeKind::LLVMPointerTypeKind && value.public {
-                let func = LLVMAddFunction(self.module,  LLVMGetValueName(value.value), value._type);
-                self.current_scope.define(name, func, value._type, false, false);
-            } else {
-                self.current_scope.define(name, value.value, value._type, false, false);
-            }
-        }
-        for (name, i) in compiler.current_scope.fields {
-            self.current_scope.fields.insert(name, i);
+        let mut compiler = Compiler::compile(Path::new(&partial), module.ast, true, self.checks, self.diagnostics);
+        if compiler.diagnostics.has_errors() {
+            return Err(Diagnostic::error(codes::INCLUDE_NOT_FOUND, format!("could not compile included file \"{}\" due to previous errors", path))
+                .with_label(stmt.span, "included here"));
         }
 
         self.libs.append(&mut compiler.libs);
         self.outputs.append(&mut compiler.outputs);
+        Ok(())
     }
 
-    unsafe fn visit_assignment_expr(&mut self, expr: Expr) {
-        let Expr::Assignment { assignee, right } = &expr else {
+    unsafe fn visit_assignment_expr(&mut self, expr: Expr) -> CompileResult<()> {
+        let ExprKind::Assignment { assignee, right } = expr.kind else {
             panic!("Expected assignment expression");
         };
 
-        let ptr = if let Expr::Symbol(name) = *assignee.clone() {
-            self.current_scope.resolve(name.clone()).unwrap()
-        } else if let Expr::Access { .. } = *assignee.clone() {
-            &self.resolve_value(*assignee.clone())
-        } else {
-            panic!("Invalid expression on LHS of assignment, got {:?}", expr);
+        let ptr = self.resolve_place(*assignee)?;
+        let val = self.resolve_value(*right)?;
+
+        LLVMBuildStore(self.builder, val.value, ptr.value);
+        Ok(())
+    }
+
+    /// The target is only evaluated once, so `a[i++] += 1` increments `i` a single time
+    unsafe fn visit_compound_assignment(&mut self, expr: Expr) -> CompileResult<()> {
+        let ExprKind::CompoundAssignment { assignee, op, right } = expr.kind else {
+            panic!("Expected compound assignment expression");
         };
 
-        let new_ptr = ptr.clone();
+        let ty = assignee.ty().clone();
+        let _type = self.llvm_type(&ty);
+        let ptr = self.resolve_place(*assignee)?;
+        let old = IGValue::new(LLVMBuildLoad2(self.builder, _type, ptr.value, gen_id()), _type);
+        let val = self.resolve_value(*right)?;
 
-        let val = self.resolve_value(*right.clone());
-        
-        if !new_ptr.mutable {
-            panic!("Attempted to assign to an immutable variable {:?}", assignee);
-        }
-        LLVMBuildStore(self.builder, val.value, new_ptr.value);
+        let new = self.visit_op(old, val, op, &ty, expr.span)?;
+        LLVMBuildStore(self.builder, new, ptr.value);
+        Ok(())
     }
 
-    unsafe fn visit_call_expr(&mut self, expr: Expr) -> Option<IGValue> {
-        let Expr::Call { name, args } = expr else {
-            panic!("Expected call expression");
+    /// Evaluates to the value before the update for postfix forms and after it for prefix ones
+    unsafe fn visit_increment(&mut self, expr: Expr) -> CompileResult<IGValue> {
+        let ty = expr.ty().clone();
+        let ExprKind::Increment { target, decrement, postfix } = expr.kind else {
+            panic!("Expected increment expression");
         };
 
-        let Some(f) = self.current_scope.resolve(name.clone()) else {
-            panic!("Failed to resolve function {:?}", name);
+        let _type = self.llvm_type(&ty);
+        let ptr = self.resolve_place(*target)?;
+        let old = LLVMBuildLoad2(self.builder, _type, ptr.value, gen_id());
+
+        let new = if ty.is_float() {
+            let one = LLVMConstReal(_type, 1.0);
+            if decrement { LLVMBuildFSub(self.builder, old, one, gen_id()) } else { LLVMBuildFAdd(self.builder, old, one, gen_id()) }
+        } else {
+            let op = if decrement { Token::Minus } else { Token::Plus };
+            self.build_int_arith(&op, old, LLVMConstInt(_type, 1, 0), ty.is_signed(), expr.span)
         };
+        LLVMBuildStore(self.builder, new, ptr.value);
+
+        Ok(IGValue::new(if postfix { old } else { new }, _type))
+    }
+
+    unsafe fn visit_call_expr(&mut self, expr: Expr) -> CompileResult<Option<IGValue>> {
+        let ret = expr.ty().clone();
+        let (name, args) = match expr.kind {
+            ExprKind::Call { name, args } => (name, self.get_arg_values(args)?),
+            ExprKind::MethodCall { receiver, name, args, by_ref } => {
+                let this = receiver.ty().struct_name().expect("Expected struct receiver").to_string();
+                let mut values = vec![self.resolve_receiver(*receiver, by_ref)?];
+                values.extend(self.get_arg_values(args)?);
+                (method_symbol(&this, &name), values)
+            },
+            _ => panic!("Expected call expression"),
+        };
+
+        let f = self.resolve_symbol(&name, expr.span)?;
 
         let f_type = f.clone()._type;
         let f_value = f.clone().value;
 
-        let args = self.get_arg_values(args);
-
-        if LLVMGetTypeKind(self.get_type_by_name("void")) == LLVMGetTypeKind(LLVMGetReturnType(f_type)) {
+        if ret.is_void() {
             LLVMBuildCall2(self.builder, f_type, f_value, args.clone().as_mut_ptr(), args.len() as u32, get_cstring("".into()));   
-            return None;
+            return Ok(None);
         }
 
-        Some(IGValue::new(LLVMBuildCall2(self.builder, f_type, f_value, args.clone().as_mut_ptr(), args.len() as u32, gen_id()), f_type))
+        Ok(Some(IGValue::new(LLVMBuildCall2(self.builder, f_type, f_value, args.clone().as_mut_ptr(), args.len() as u32, gen_id()), self.llvm_type(&ret))))
     }
 
-    unsafe fn visit_variable_declaration(&mut self, stmt: Stmt, define: bool) {
-        let Stmt::VariableDeclaration { name, explicit_type, value } = stmt else {
+    unsafe fn visit_variable_declaration(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::VariableDeclaration { name, value, .. } = stmt.kind else {
             panic!("Expected variable declaration");
         };
 
-        if !self.current_scope.resolve(name.clone()).is_some() {
-            let val = self.resolve_value(*value);
-            let alloca = LLVMBuildAlloca(self.builder, val._type, gen_id_pre(name.clone()));
-            LLVMBuildStore(self.builder, val.value, alloca);
-            if define {
-                if val.parent.is_some() {
-                    self.current_scope.define_struct(name, val.value, val._type, true, true, val.parent.unwrap());
-                } else {
-                    self.current_scope.define(name, alloca, val._type, true, true);
-                }
-            }
-        } else {
-            panic!("Cannot redefine variable {:?}", name);
-        }
+        let _type = self.llvm_type(value.ty());
+        let val = self.resolve_value(*value)?;
+        let alloca = self.build_alloca(_type, gen_id_pre(name.clone()));
+        LLVMBuildStore(self.builder, val.value, alloca);
+        self.current_scope.define(name, alloca, _type);
+        Ok(())
     }
 
-    unsafe fn get_arg_types(&self, args: Vec<Stmt>) -> Vec<LLVMTypeRef> {
+    unsafe fn get_arg_types(&self, args: &[Stmt]) -> Vec<LLVMTypeRef> {
         let mut types = vec![];
         for arg in args {
-            let Stmt::Field { name, _type } = arg else {
+            let StmtKind::Field { _type, .. } = &arg.kind else {
                 panic!("Expected field in args");
             };
 
-            types.push(self.get_type(*_type));
+            types.push(self.get_type(_type));
         }
         types
     }
 
     
-    unsafe fn get_arg_values(&mut self, args: Vec<Expr>) -> Vec<LLVMValueRef> {
+    unsafe fn get_arg_values(&mut self, args: Vec<Expr>) -> CompileResult<Vec<LLVMValueRef>> {
         let mut values = vec![];
         for arg in args {
-            values.push(self.resolve_value(arg).value);
+            values.push(self.resolve_value(arg)?.value);
         }
-        values
+        Ok(values)
     }
 
-    unsafe fn visit_function_declaration(&mut self, stmt: Stmt) {
-        let Stmt::FunctionDeclaration { name, return_type, arguments, body } = stmt else {
+    unsafe fn visit_function_declaration(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::FunctionDeclaration { name, return_type, arguments, body } = stmt.kind else {
             panic!("Expected function declaration");
         };
 
-        let arg_types = self.get_arg_types(arguments.clone());
-        let mut ret_type = self.get_type(*return_type.clone());
-
-        if self.get_type_by_name("void") == ret_type && name == "main" {
-            ret_type = self.get_type(Type::Symbol("i32".into()));
-        }
-    
-        let func_type = LLVMFunctionType(ret_type, arg_types.clone().as_mut_ptr(), arg_types.len() as u32, 0);
-        let func = LLVMAddFunction(self.module, get_cstring(name.clone()), func_type);
+        let arg_types = self.get_arg_types(&arguments);
+        let func_type = self.get_function_type(&return_type, &arguments, name == "main");
+        let func = self.get_or_add_function(name.clone(), func_type);
         let block = LLVMAppendBasicBlockInContext(self.context, func, gen_id_prepost(name.clone(), "ignis_entry".into()));
 
-        let outer_scope = self.current_scope.clone();
-
-        self.current_scope = IGScope::new(None, None, Some(name.clone()), Some(Box::new(outer_scope.clone())));
+        // Parameters get a frame of their own which the body can shadow
+        self.current_scope.push();
         LLVMPositionBuilderAtEnd(self.builder, block);
         
         for (i, s) in arguments.iter().enumerate() {
-            let Stmt::Field { name, _type } = s else {
+            let StmtKind::Field { name, _type } = &s.kind else {
                 panic!("Expected field in args");
             };
 
             let t = arg_types[i];
-            let alloca = LLVMBuildAlloca(self.builder, t, gen_id_pre(name.clone()));
+            let alloca = self.build_alloca(t, gen_id_pre(name.clone()));
             LLVMBuildStore(self.builder, LLVMGetParam(func, i as u32), alloca);
-            self.current_scope.define(name.clone(), alloca, t, false, true);
+            self.current_scope.define(name.clone(), alloca, t);
         }
 
-        self.current_scope.define(name.clone(), func, func_type, false, true);
-        self.visit_block(*body);
-
-        if self.get_type_by_name("void") == self.get_type(*return_type) && name == "main" {
-            LLVMBuildRet(self.builder, self.resolve_value(Expr::Int(0)).value);
-        } else if self.get_type_by_name("void") == ret_type {
-            LLVMBuildRetVoid(self.builder);
+        let visited = self.visit_block(*body);
+        self.current_scope.pop();
+        visited?;
 
+        if !self.is_terminated() {
+            if return_type.ty().is_void() && name == "main" {
+                LLVMBuildRet(self.builder, LLVMConstInt(self.llvm_type(&Ty::Int { bits: 32, signed: true }), 0, 0));
+            } else if return_type.ty().is_void() {
+                LLVMBuildRetVoid(self.builder);
+            } else {
+                // The checker made sure every path returns, so whatever falls through here can't be reached
+                LLVMBuildUnreachable(self.builder);
+            }
         }
 
-        self.current_scope = outer_scope;
-        self.current_scope.define(name.clone(), func, func_type, false, true);
+        Ok(())
     }
 
-    unsafe fn visit_return(&mut self, stmt: Stmt) {
-        let Stmt::Return { value } = stmt else {
+    unsafe fn visit_return(&mut self, stmt: Stmt) -> CompileResult<()> {
+        let StmtKind::Return { value } = stmt.kind else {
             panic!("Expected return");
         };
 
-        LLVMBuildRet(self.builder, self.resolve_value(*value).value);
+        match value {
+            Some(value) => {
+                LLVMBuildRet(self.builder, self.resolve_value(*value)?.value);
+            },
+            None => {
+                // A `void` main still gives the OS an exit code
+                let ret = LLVMGetReturnType(LLVMGlobalGetValueType(self.get_current_function()));
+                if let LLVMTypeKind::LLVMVoidTypeKind = LLVMGetTypeKind(ret) {
+                    LLVMBuildRetVoid(self.builder);
+                } else {
+                    LLVMBuildRet(self.builder, LLVMConstInt(ret, 0, 0));
+                }
+            },
+        }
+        self.start_dead_block("after_return");
+        Ok(())
     } 
 
     
-    unsafe fn visit_op(&mut self, left: IGValue, right: IGValue, op: Token, floating: bool) -> LLVMValueRef {
+    unsafe fn visit_op(&mut self, left: IGValue, right: IGValue, op: Token, operands: &Ty, span: Span) -> CompileResult<LLVMValueRef> {
         let name = gen_id_pre("op".into());
         let lhs = left.value;
         let rhs = right.value;
-
-        match op {
-            Token::Plus => if floating { LLVMBuildFAdd(self.builder, lhs, rhs, name) } else { LLVMBuildAdd(self.builder, lhs, rhs, name) }
-            Token::Minus => if floating { LLVMBuildFSub(self.builder, lhs, rhs, name) } else { LLVMBuildSub(self.builder, lhs, rhs, name) }
-            Token::Multiply => if floating { LLVMBuildFMul(self.builder, lhs, rhs, name) } else { LLVMBuildMul(self.builder, lhs, rhs, name) }
-            Token::Divide => if floating { LLVMBuildFDiv(self.builder, lhs, rhs, name) } else { LLVMBuildSDiv(self.builder, lhs, rhs, name) }
-            Token::Greater => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealUGT, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, IPredicate::LLVMIntUGT, lhs, rhs, name) }
-            Token::GreaterOrEqual => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealUGE, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, IPredicate::LLVMIntUGE, lhs, rhs, name) }
-            Token::Less => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealULT, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, IPredicate::LLVMIntULT, lhs, rhs, name) }
-            Token::LessOrEqual => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealULE, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, IPredicate::LLVMIntULE, lhs, rhs, name) }
+        // Both sides were checked to have the same type
+        let floating = operands.is_float();
+        let signed = operands.is_signed();
+
+        Ok(match op {
+            Token::Plus => if floating { LLVMBuildFAdd(self.builder, lhs, rhs, name) } else { self.build_int_arith(&op, lhs, rhs, signed, span) }
+            Token::Minus => if floating { LLVMBuildFSub(self.builder, lhs, rhs, name) } else { self.build_int_arith(&op, lhs, rhs, signed, span) }
+            Token::Multiply => if floating { LLVMBuildFMul(self.builder, lhs, rhs, name) } else { self.build_int_arith(&op, lhs, rhs, signed, span) }
+            Token::Divide | Token::Mod if !floating => {
+                self.build_division_check(lhs, rhs, signed, matches!(op, Token::Mod), span);
+                match (op, signed) {
+                    (Token::Divide, true) => LLVMBuildSDiv(self.builder, lhs, rhs, name),
+                    (Token::Divide, false) => LLVMBuildUDiv(self.builder, lhs, rhs, name),
+                    (_, true) => LLVMBuildSRem(self.builder, lhs, rhs, name),
+                    (_, false) => LLVMBuildURem(self.builder, lhs, rhs, name),
+                }
+            },
+            Token::Divide => LLVMBuildFDiv(self.builder, lhs, rhs, name),
+            Token::Greater => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealUGT, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, if signed { IPredicate::LLVMIntSGT } else { IPredicate::LLVMIntUGT }, lhs, rhs, name) }
+            Token::GreaterOrEqual => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealUGE, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, if signed { IPredicate::LLVMIntSGE } else { IPredicate::LLVMIntUGE }, lhs, rhs, name) }
+            Token::Less => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealULT, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, if signed { IPredicate::LLVMIntSLT } else { IPredicate::LLVMIntULT }, lhs, rhs, name) }
+            Token::LessOrEqual => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealULE, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, if signed { IPredicate::LLVMIntSLE } else { IPredicate::LLVMIntULE }, lhs, rhs, name) }
             Token::Equals => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealUEQ, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, IPredicate::LLVMIntEQ, lhs, rhs, name) }
             Token::NotEquals => if floating { LLVMBuildFCmp(self.builder, FPredicate::LLVMRealUNE, lhs, rhs, name) } else { LLVMBuildICmp(self.builder, IPredicate::LLVMIntNE, lhs, rhs, name) }
-            Token::Or => LLVMBuildOr(self.builder, lhs, rhs, name),
-            Token::And => LLVMBuildAnd(self.builder, lhs, rhs, name),
-            _ => panic!("Invalid operation {:?}", op),
-        }
+            Token::Mod => LLVMBuildFRem(self.builder, lhs, rhs, name),
+            Token::Power(_) => if floating { self.build_float_pow(lhs, rhs, left._type) } else { self.build_int_pow(lhs, rhs, left._type, signed, span) }
+            Token::BitOr => LLVMBuildOr(self.builder, lhs, rhs, name),
+            Token::Reference => LLVMBuildAnd(self.builder, lhs, rhs, name),
+            Token::BitXor => LLVMBuildXor(self.builder, lhs, rhs, name),
+            Token::ShiftLeft => {
+                self.build_shift_check(rhs, "shift left", span);
+                LLVMBuildShl(self.builder, lhs, rhs, name)
+            },
+            Token::ShiftRight => {
+                self.build_shift_check(rhs, "shift right", span);
+                if signed { LLVMBuildAShr(self.builder, lhs, rhs, name) } else { LLVMBuildLShr(self.builder, lhs, rhs, name) }
+            },
+            _ => return Err(Diagnostic::error(codes::UNSUPPORTED, format!("operator {} is not supported yet", op))
+                .with_label(span, "unsupported operator")),
+        })
     }
 
-    unsafe fn visit_binexpr(&mut self, binexpr: Expr) -> IGValue {
-        let Expr::Binary { left, op, right } = binexpr else {
-            panic!("Expected binary expression");
+    /// Integer `+`, `-` or `*`, which panics on overflow when checks are on and wraps around otherwise
+    unsafe fn build_int_arith(&mut self, op: &Token, lhs: LLVMValueRef, rhs: LLVMValueRef, signed: bool, span: Span) -> LLVMValueRef {
+        let (intrinsic, verb) = match op {
+            Token::Plus => ("add", "add"),
+            Token::Minus => ("sub", "subtract"),
+            Token::Multiply => ("mul", "multiply"),
+            _ => panic!("Expected arithmetic operator"),
         };
 
-        let lvalue = self.resolve_value(*left.clone());
-        let rvalue = self.resolve_value(*right.clone());
+        if !self.checks {
+            let name = gen_id_pre("op".into());
+            return match op {
+                Token::Plus => LLVMBuildAdd(self.builder, lhs, rhs, name),
+                Token::Minus => LLVMBuildSub(self.builder, lhs, rhs, name),
+                _ => LLVMBuildMul(self.builder, lhs, rhs, name),
+            };
+        }
 
-        let _type: LLVMTypeRef;
-        
-        fn is_bool(op: &Token) -> bool {
-            is_kind(op, &Token::Equals) |
-            is_kind(op, &Token::NotEquals) |
-            is_kind(op, &Token::Less) |
-            is_kind(op, &Token::LessOrEqual) |
-            is_kind(op, &Token::Greater) |
-            is_kind(op, &Token::GreaterOrEqual) |
-            is_kind(op, &Token::Not) |
-            is_kind(op, &Token::Or) |
-            is_kind(op, &Token::And)
-        }
-
-        if is_bool(&op) {
-            _type = self.get_type_by_name("bool");
+        self.build_overflow_checked(&format!("{}{}", if signed { "s" } else { "u" }, intrinsic), lhs, rhs, verb, span)
+    }
+
+    /// Calls `llvm.<intrinsic>.with.overflow` and panics when it reports an overflow
+    unsafe fn build_overflow_checked(&mut self, intrinsic: &str, lhs: LLVMValueRef, rhs: LLVMValueRef, verb: &str, span: Span) -> LLVMValueRef {
+        let (value, overflow) = self.build_with_overflow(intrinsic, lhs, rhs);
+        let fine = LLVMBuildNot(self.builder, overflow, gen_id());
+        self.build_check(fine, span, &format!("attempt to {} with overflow", verb), &[]);
+        value
+    }
+
+    /// Calls `llvm.<intrinsic>.with.overflow`, giving the wrapped result and whether it overflowed
+    unsafe fn build_with_overflow(&mut self, intrinsic: &str, lhs: LLVMValueRef, rhs: LLVMValueRef) -> (LLVMValueRef, LLVMValueRef) {
+        let _type = LLVMTypeOf(lhs);
+        let mut fields = [_type, LLVMIntTypeInContext(self.context, 1)];
+        let ret_type = LLVMStructTypeInContext(self.context, fields.as_mut_ptr(), 2, 0);
+        let mut params = [_type, _type];
+        let func_type = LLVMFunctionType(ret_type, params.as_mut_ptr(), 2, 0);
+        let func = self.get_or_add_function(format!("llvm.{}.with.overflow.i{}", intrinsic, LLVMGetIntTypeWidth(_type)), func_type);
+
+        let mut args = [lhs, rhs];
+        let result = LLVMBuildCall2(self.builder, func_type, func, args.as_mut_ptr(), 2, gen_id());
+        let overflow = LLVMBuildExtractValue(self.builder, result, 1, gen_id());
+        (LLVMBuildExtractValue(self.builder, result, 0, gen_id_pre("op".into())), overflow)
+    }
+
+    /// Panics when dividing by zero, or for signed integers when dividing the smallest value by -1 since the result doesn't fit
+    unsafe fn build_division_check(&mut self, lhs: LLVMValueRef, rhs: LLVMValueRef, signed: bool, remainder: bool, span: Span) {
+        if !self.checks {
+            return;
+        }
+
+        let _type = LLVMTypeOf(rhs);
+        let nonzero = LLVMBuildICmp(self.builder, IPredicate::LLVMIntNE, rhs, LLVMConstInt(_type, 0, 0), gen_id());
+        let message = if remainder {
+            "attempt to calculate the remainder with a divisor of zero"
         } else {
-            _type = lvalue._type;
+            "attempt to divide by zero"
+        };
+        self.build_check(nonzero, span, message, &[]);
+
+        if signed {
+            let min = LLVMConstInt(_type, 1 << (LLVMGetIntTypeWidth(_type) - 1), 0);
+            let is_min = LLVMBuildICmp(self.builder, IPredicate::LLVMIntEQ, lhs, min, gen_id());
+            let is_minus_one = LLVMBuildICmp(self.builder, IPredicate::LLVMIntEQ, rhs, LLVMConstAllOnes(_type), gen_id());
+            let overflow = LLVMBuildAnd(self.builder, is_min, is_minus_one, gen_id());
+            let verb = if remainder { "calculate the remainder" } else { "divide" };
+            self.build_check(LLVMBuildNot(self.builder, overflow, gen_id()), span, &format!("attempt to {} with overflow", verb), &[]);
         }
+    }
+
+    /// Panics when shifting by the bit width or more, `rhs` is treated as unsigned so negative amounts are caught too
+    unsafe fn build_shift_check(&mut self, rhs: LLVMValueRef, verb: &str, span: Span) {
+        if !self.checks {
+            return;
+        }
+
+        let _type = LLVMTypeOf(rhs);
+        let bits = LLVMConstInt(_type, LLVMGetIntTypeWidth(_type) as u64, 0);
+        let fine = LLVMBuildICmp(self.builder, IPredicate::LLVMIntULT, rhs, bits, gen_id());
+        self.build_check(fine, span, &format!("attempt to {} with overflow", verb), &[]);
+    }
+
+    /// Converts a value between types for casts and implicit widening, the checker decides which conversions are allowed
+    unsafe fn build_conversion(&mut self, value: LLVMValueRef, from: &Ty, to: &Ty) -> LLVMValueRef {
+        let _type = self.llvm_type(to);
+        let name = gen_id_pre("conv".into());
+        let scalar = Ty::Int { bits: 32, signed: false };
+        match (from, to) {
+            _ if from == to => value,
+            (Ty::Char, _) => self.build_conversion(value, &scalar, to),
+            (_, Ty::Char) => self.build_conversion(value, from, &scalar),
+            (Ty::Int { bits, .. }, Ty::Int { bits: to_bits, .. }) if bits > to_bits => LLVMBuildTrunc(self.builder, value, _type, name),
+            (Ty::Int { bits, .. }, Ty::Int { bits: to_bits, .. }) if bits == to_bits => value,
+            (Ty::Int { signed: true, .. }, Ty::Int { .. }) => LLVMBuildSExt(self.builder, value, _type, name),
+            (Ty::Int { .. } | Ty::Bool, Ty::Int { .. }) => LLVMBuildZExt(self.builder, value, _type, name),
+            (Ty::Int { signed: true, .. }, Ty::Float { .. }) => LLVMBuildSIToFP(self.builder, value, _type, name),
+            (Ty::Int { .. }, Ty::Float { .. }) => LLVMBuildUIToFP(self.builder, value, _type, name),
+            (Ty::Float { .. }, Ty::Int { signed: true, .. }) => LLVMBuildFPToSI(self.builder, value, _type, name),
+            (Ty::Float { .. }, Ty::Int { .. }) => LLVMBuildFPToUI(self.builder, value, _type, name),
+            (Ty::Float { bits }, Ty::Float { bits: to_bits }) if bits > to_bits => LLVMBuildFPTrunc(self.builder, value, _type, name),
+            (Ty::Float { .. }, Ty::Float { .. }) => LLVMBuildFPExt(self.builder, value, _type, name),
+            (Ty::Ref(_), Ty::Ref(_)) => LLVMBuildBitCast(self.builder, value, _type, name),
+            (Ty::Slice { .. }, Ty::Ref(_)) => {
+                let ptr = LLVMBuildExtractValue(self.builder, value, 0, gen_id_pre("ptr".into()));
+                LLVMBuildBitCast(self.builder, ptr, _type, name)
+            },
+            (Ty::Ref(_), Ty::Int { .. }) => LLVMBuildPtrToInt(self.builder, value, _type, name),
+            (Ty::Int { .. }, Ty::Ref(_)) => LLVMBuildIntToPtr(self.builder, value, _type, name),
+            _ => unreachable!("the checker only allows conversions that `Ty::casts_to` accepts, not {} to {}", from, to),
+        }
+    }
+
+    unsafe fn build_float_pow(&mut self, base: LLVMValueRef, exponent: LLVMValueRef, _type: LLVMTypeRef) -> LLVMValueRef {
+        let suffix = match LLVMGetTypeKind(_type) {
+            LLVMTypeKind::LLVMHalfTypeKind => "f16",
+            LLVMTypeKind::LLVMFloatTypeKind => "f32",
+            _ => "f64",
+        };
 
-        if lvalue.are_both(rvalue.clone(), self.get_type_by_name("i32")) {
-            IGValue::new(self.visit_op(lvalue, rvalue, op, false), _type)
-        } else if lvalue.are_both(rvalue.clone(), self.get_type_by_name("f32")) {
-            IGValue::new(self.visit_op(lvalue, rvalue, op, true), _type)
+        let mut params = [_type, _type];
+        let func_type = LLVMFunctionType(_type, params.as_mut_ptr(), 2, 0);
+        let func = self.get_or_add_function(format!("llvm.pow.{}", suffix), func_type);
+
+        let mut args = [base, exponent];
+        LLVMBuildCall2(self.builder, func_type, func, args.as_mut_ptr(), 2, gen_id_pre("pow".into()))
+    }
+
+    /// Exponentiation by squaring. A negative exponent panics when checks are on, otherwise it gives
+    /// what integer division of 1 by the power would, which is 0 unless the base is 1 or -1
+    unsafe fn build_int_pow(&mut self, base: LLVMValueRef, exponent: LLVMValueRef, _type: LLVMTypeRef, signed: bool, span: Span) -> LLVMValueRef {
+        let header = self.create_basic_block("pow_header".into());
+        let body = self.create_basic_block("pow_body".into());
+        let exit = self.create_basic_block("pow_exit".into());
+        let zero = LLVMConstInt(_type, 0, 0);
+        let one = LLVMConstInt(_type, 1, 0);
+        if signed {
+            let positive = LLVMBuildICmp(self.builder, IPredicate::LLVMIntSGE, exponent, zero, gen_id());
+            self.build_check(positive, span, "attempt to raise to a negative power", &[]);
+        }
+        // The check may have moved on to a block of its own
+        let entry = LLVMGetInsertBlock(self.builder);
+        LLVMBuildBr(self.builder, header);
+
+        LLVMPositionBuilderAtEnd(self.builder, header);
+        let result = LLVMBuildPhi(self.builder, _type, gen_id());
+        let factor = LLVMBuildPhi(self.builder, _type, gen_id());
+        let remaining = LLVMBuildPhi(self.builder, _type, gen_id());
+        let predicate = if signed { IPredicate::LLVMIntSGT } else { IPredicate::LLVMIntNE };
+        let cond = LLVMBuildICmp(self.builder, predicate, remaining, zero, gen_id());
+        LLVMBuildCondBr(self.builder, cond, body, exit);
+
+        LLVMPositionBuilderAtEnd(self.builder, body);
+        let odd = LLVMBuildICmp(self.builder, IPredicate::LLVMIntNE, LLVMBuildAnd(self.builder, remaining, one, gen_id()), zero, gen_id());
+        let next_remaining = LLVMBuildLShr(self.builder, remaining, one, gen_id());
+        let (product, next_factor) = if self.checks {
+            let intrinsic = if signed { "smul" } else { "umul" };
+            let (product, product_overflow) = self.build_with_overflow(intrinsic, result, factor);
+            let (square, square_overflow) = self.build_with_overflow(intrinsic, factor, factor);
+
+            // Only overflows in what ends up in the result count, the square after the last bit is never used
+            let more = LLVMBuildICmp(self.builder, IPredicate::LLVMIntNE, next_remaining, zero, gen_id());
+            let overflow = LLVMBuildOr(
+                self.builder,
+                LLVMBuildAnd(self.builder, odd, product_overflow, gen_id()),
+                LLVMBuildAnd(self.builder, more, square_overflow, gen_id()),
+                gen_id(),
+            );
+            let fine = LLVMBuildNot(self.builder, overflow, gen_id());
+            self.build_check(fine, span, "attempt to raise to a power with overflow", &[]);
+            (product, square)
         } else {
-            panic!("Unsupported operation '{:?}' between {:?} and {:?}", op, left, right);
+            (LLVMBuildMul(self.builder, result, factor, gen_id()), LLVMBuildMul(self.builder, factor, factor, gen_id()))
+        };
+        let next_result = LLVMBuildSelect(self.builder, odd, product, result, gen_id());
+        // The check leaves the body in a block of its own
+        let body_end = LLVMGetInsertBlock(self.builder);
+        LLVMBuildBr(self.builder, header);
+
+        let mut values = [one, next_result];
+        let mut blocks = [entry, body_end];
+        LLVMAddIncoming(result, values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+        let mut values = [base, next_factor];
+        LLVMAddIncoming(factor, values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+        let mut values = [exponent, next_remaining];
+        LLVMAddIncoming(remaining, values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+
+        LLVMPositionBuilderAtEnd(self.builder, exit);
+        if !signed {
+            return result;
         }
+
+        // The loop never runs for a negative exponent, pick 1, -1 or 0 from the base instead
+        let minus_one = LLVMConstInt(_type, u64::MAX, 1);
+        let odd = LLVMBuildICmp(self.builder, IPredicate::LLVMIntNE, LLVMBuildAnd(self.builder, exponent, one, gen_id()), zero, gen_id());
+        let is_one = LLVMBuildICmp(self.builder, IPredicate::LLVMIntEQ, base, one, gen_id());
+        let is_minus_one = LLVMBuildICmp(self.builder, IPredicate::LLVMIntEQ, base, minus_one, gen_id());
+        let unit = LLVMBuildSelect(self.builder, odd, minus_one, one, gen_id());
+        let fraction = LLVMBuildSelect(self.builder, is_minus_one, unit, LLVMBuildSelect(self.builder, is_one, one, zero, gen_id()), gen_id());
+        let negative = LLVMBuildICmp(self.builder, IPredicate::LLVMIntSLT, exponent, zero, gen_id());
+        LLVMBuildSelect(self.builder, negative, fraction, result, gen_id())
     }
 
-    unsafe fn resolve_mutable_symbol(&mut self, expr: Expr) -> IGValue {
-        let Expr::Symbol(symbol) = expr else {
-            panic!("Expectd symbol but got {:?}", expr);
+    unsafe fn visit_binexpr(&mut self, binexpr: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(binexpr.ty());
+        let ExprKind::Binary { left, op, right } = binexpr.kind else {
+            panic!("Expected binary expression");
         };
 
-        let Some(val) = self.current_scope.resolve(symbol.clone()) else {
-            panic!("Failed to resolve symbol: {:?}", symbol);
+        if let Token::And | Token::Or = op {
+            return self.visit_logical(*left, op, *right);
+        }
+
+        let operands = left.ty().clone();
+        let lvalue = self.resolve_value(*left)?;
+        let rvalue = self.resolve_value(*right)?;
+
+        Ok(IGValue::new(self.visit_op(lvalue, rvalue, op, &operands, binexpr.span)?, _type))
+    }
+
+    /// `&&` and `||` only evaluate their right side when the left doesn't already decide the result
+    unsafe fn visit_logical(&mut self, left: Expr, op: Token, right: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(&Ty::Bool);
+        let is_and = matches!(op, Token::And);
+
+        let lhs = self.resolve_value(left)?;
+        let lhs_block = LLVMGetInsertBlock(self.builder);
+        let rhs_block = self.create_basic_block(if is_and { "and_rhs" } else { "or_rhs" }.into());
+        let merge = self.create_basic_block(if is_and { "and_merge" } else { "or_merge" }.into());
+
+        if is_and {
+            LLVMBuildCondBr(self.builder, lhs.value, rhs_block, merge);
+        } else {
+            LLVMBuildCondBr(self.builder, lhs.value, merge, rhs_block);
+        }
+
+        LLVMPositionBuilderAtEnd(self.builder, rhs_block);
+        let rhs = self.resolve_value(right)?;
+        // The right side can span blocks of its own, the phi needs the one it ended in
+        let rhs_end = LLVMGetInsertBlock(self.builder);
+        LLVMBuildBr(self.builder, merge);
+
+        LLVMPositionBuilderAtEnd(self.builder, merge);
+        let phi = LLVMBuildPhi(self.builder, _type, gen_id());
+        let mut values = [LLVMConstInt(_type, !is_and as u64, 0), rhs.value];
+        let mut blocks = [lhs_block, rhs_end];
+        LLVMAddIncoming(phi, values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+
+        Ok(IGValue::new(phi, _type))
+    }
+
+    unsafe fn visit_ternary(&mut self, expr: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(expr.ty());
+        let ExprKind::Ternary { condition, then, _else } = expr.kind else {
+            panic!("Expected ternary expression");
         };
-        IGValue::new(
-            LLVMBuildLoad2(self.builder, LLVMPointerType(val._type, 0), val.value, gen_id()),
-            val._type
-        )
+
+        let cond = self.visit_conditional(*condition)?;
+
+        // Arms that can't fail or have side effects are both evaluated and picked between
+        if is_speculatable(&then, self.checks) && is_speculatable(&_else, self.checks) {
+            let then = self.resolve_value(*then)?;
+            let _else = self.resolve_value(*_else)?;
+            return Ok(IGValue::new(LLVMBuildSelect(self.builder, cond.value, then.value, _else.value, gen_id()), _type));
+        }
+
+        let then_block = self.create_basic_block("ternary_then".into());
+        let else_block = self.create_basic_block("ternary_else".into());
+        let merge = self.create_basic_block("ternary_merge".into());
+        LLVMBuildCondBr(self.builder, cond.value, then_block, else_block);
+
+        LLVMPositionBuilderAtEnd(self.builder, then_block);
+        let then = self.resolve_value(*then)?;
+        let then_end = LLVMGetInsertBlock(self.builder);
+        LLVMBuildBr(self.builder, merge);
+
+        LLVMPositionBuilderAtEnd(self.builder, else_block);
+        let _else = self.resolve_value(*_else)?;
+        let else_end = LLVMGetInsertBlock(self.builder);
+        LLVMBuildBr(self.builder, merge);
+
+        LLVMPositionBuilderAtEnd(self.builder, merge);
+        let phi = LLVMBuildPhi(self.builder, _type, gen_id());
+        let mut values = [then.value, _else.value];
+        let mut blocks = [then_end, else_end];
+        LLVMAddIncoming(phi, values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+
+        Ok(IGValue::new(phi, _type))
     }
 
-    unsafe fn resolve_value(&mut self, value: Expr) -> IGValue {
-        if let Expr::Int(i) = value {
-            let _type = self.get_type_by_name("i32");
-            IGValue::new(LLVMConstInt(_type, i as u64, 0), _type)
-        } else if let Expr::Float(f) = value {
-            let _type = self.get_type_by_name("f32");
-            IGValue::new(LLVMConstReal(_type, f), _type)
-        } else if let Expr::Symbol(symbol) = value {
-            let Some(val) = self.current_scope.resolve(symbol.clone()) else {
-                panic!("Failed to resolve symbol: {:?}", symbol);
-            };
-            IGValue::new(
-                LLVMBuildLoad2(self.builder, val._type, val.value, gen_id()),
-                val._type
-            )
-        } else if let Expr::Bool(b) = value {
-            let _type = self.get_type_by_name("bool");
-            IGValue::new(LLVMConstInt(_type, b as u64, 0), _type)
-        } else if let Expr::Binary { .. } = value {
-            self.visit_binexpr(value)
-        } else if let Expr::String(s) = value.clone() {
-            let _type = LLVMPointerType(self.get_type_by_name("i8"), 0);
-            let val = LLVMBuildPointerCast(self.builder, LLVMBuildGlobalString(self.builder, get_cstring(s), gen_id()), _type, gen_id());
-            IGValue::new(val, _type)
-        } else if let Expr::Call { .. } = value.clone() {
-            self.visit_call_expr(value).expect("Expected value from call expression")
-        } else if let Expr::StructInitialize { name, fields } = value.clone() {
-            let _type = self.current_scope.resolve(name.clone()).expect(&format!("Failed to find type '{}' in context", name)).clone();
-            let alloca = LLVMBuildAlloca(self.builder, _type._type, gen_id());
-
-            for (i, field) in fields.iter().enumerate() {
-                let Stmt::StructInitField { name, value } = field else {
-                    panic!("Expected struct init field");
+    fn resolve_symbol(&self, symbol: &str, span: Span) -> CompileResult<&IGValue> {
+        self.current_scope.resolve(symbol.into()).ok_or_else(|| {
+            Diagnostic::error(codes::UNRESOLVED_SYMBOL, format!("cannot find `{}` in this scope", symbol))
+                .with_label(span, "not found in this scope")
+        })
+    }
+
+    /// Resolves an expression that names a storage location, returning a
+    /// pointer to it along with the type stored there
+    unsafe fn resolve_place(&mut self, expr: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(expr.ty());
+        match expr.kind {
+            ExprKind::Symbol(symbol) => Ok(self.resolve_symbol(&symbol, expr.span)?.clone()),
+            ExprKind::Access { lhs, rhs } => {
+                let ExprKind::Symbol(fname) = rhs.kind else {
+                    panic!("Expected symbol on RHS of member access expression");
                 };
+                let sname = lhs.ty().struct_name().expect("Expected struct on LHS of member access expression").to_string();
+                let struct_type = self.llvm_type(&Ty::Struct(sname.clone()));
 
-                let val = self.resolve_value(*value.clone());
+                // Fields behind a reference are reached through the pointer it holds, anything
+                // else is addressed where it's stored, which is a fresh slot for temporaries
+                let base = if let Ty::Ref(_) = lhs.ty() {
+                    self.resolve_value(*lhs)?.value
+                } else {
+                    self.resolve_address(*lhs)?
+                };
+                let index = *self.current_scope.resolve_field(format!("{}.{}", sname, fname)).expect("Field was not checked");
+
+                let ptr = LLVMBuildStructGEP2(self.builder, struct_type, base, index as u32, gen_id());
+                Ok(IGValue::new(ptr, _type))
+            },
+            ExprKind::Index { target, index } => {
+                let signed = index.ty().is_signed();
+                let (base, len) = self.resolve_elements(*target)?;
+                let index = self.resolve_index(*index)?;
+                self.build_bounds_check(index, len, signed, expr.span);
+
+                let mut indices = [index];
+                let ptr = LLVMBuildInBoundsGEP2(self.builder, _type, base, indices.as_mut_ptr(), 1, gen_id());
+                Ok(IGValue::new(ptr, _type))
+            },
+            _ => Err(Diagnostic::error(codes::INVALID_ASSIGNMENT_TARGET, "expected a variable, field or element")
+                .with_label(expr.span, "not a storage location")),
+        }
+    }
 
-                let ptr = LLVMBuildStructGEP2(self.builder, _type._type, alloca, i as u32, gen_id());
-                LLVMBuildStore(self.builder, val.value, ptr);
-            }
+    /// Pointer to the first element of an array or slice along with how many elements there are
+    unsafe fn resolve_elements(&mut self, expr: Expr) -> CompileResult<(LLVMValueRef, LLVMValueRef)> {
+        let usize = self.llvm_type(&Ty::from_name("usize").unwrap());
+        if let Ty::Slice { .. } = expr.ty() {
+            let slice = self.resolve_value(expr)?.value;
+            let ptr = LLVMBuildExtractValue(self.builder, slice, 0, gen_id_pre("ptr".into()));
+            let len = LLVMBuildExtractValue(self.builder, slice, 1, gen_id_pre("len".into()));
+            return Ok((ptr, len));
+        }
 
-            IGValue::new_struct(alloca, _type._type, name)
-        } else if let Expr::Access { lhs, rhs } = value.clone() {
-            let Expr::Symbol(sname) = *lhs else {
-                panic!("Only single depth access expressions allowed right now");
-            };
-            let Expr::Symbol(fname) = *rhs else {
-                panic!("Expected symbol on RHS of member access expression");
-            };
+        let len = expr.ty().array_len().expect("Expected array");
+        let array_type = match expr.ty() {
+            Ty::Ref(inner) => self.llvm_type(inner),
+            ty => self.llvm_type(ty),
+        };
+        let base = if let Ty::Ref(_) = expr.ty() {
+            self.resolve_value(expr)?.value
+        } else {
+            self.resolve_address(expr)?
+        };
 
-            
-            let val = self.current_scope.resolve(sname.clone());
-            let Some(value) = val else {
-                panic!("Failed to resolve {:?}", sname);
-            };
+        let mut indices = [LLVMConstInt(usize, 0, 0), LLVMConstInt(usize, 0, 0)];
+        let ptr = LLVMBuildInBoundsGEP2(self.builder, array_type, base, indices.as_mut_ptr(), 2, gen_id());
+        Ok((ptr, LLVMConstInt(usize, len as u64, 0)))
+    }
 
-            println!("PARENT {} {:?}", sname, value);
-            let Some(parent) = value.parent.clone() else {
-                panic!("Failed to get member type")
-            };
+    /// Index or slice bound as a `usize`
+    unsafe fn resolve_index(&mut self, index: Expr) -> CompileResult<LLVMValueRef> {
+        let from = index.ty().clone();
+        let value = self.resolve_value(index)?;
+        Ok(self.build_conversion(value.value, &from, &Ty::from_name("usize").unwrap()))
+    }
 
-            let mut fullname = parent.clone();
-            fullname.push('.');
-            fullname.push_str(&fname);
+    unsafe fn visit_slice(&mut self, expr: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(expr.ty());
+        let elem_type = self.llvm_type(expr.ty().element().expect("Expected slice"));
+        let ExprKind::Slice { target, start, end } = expr.kind else {
+            panic!("Expected slice expression");
+        };
 
-            let Some(index) = self.current_scope.resolve_field(fullname.clone()) else {
-                panic!("Failed to resolve member {}", fullname);
-            };
+        // A plain reference has no length to check against, the checker made sure the end is given
+        let (base, len) = match target.ty() {
+            Ty::Ref(inner) if inner.array_len().is_none() => (self.resolve_value(*target)?.value, None),
+            _ => {
+                let (base, len) = self.resolve_elements(*target)?;
+                (base, Some(len))
+            },
+        };
+        let start = match start {
+            Some(start) => self.resolve_index(*start)?,
+            None => LLVMConstInt(self.llvm_type(&Ty::from_name("usize").unwrap()), 0, 0),
+        };
+        let end = match end {
+            Some(end) => self.resolve_index(*end)?,
+            None => len.expect("Slice of a reference has no end"),
+        };
+        let len = len.unwrap_or(end);
+
+        let ordered = LLVMBuildICmp(self.builder, IPredicate::LLVMIntULE, start, end, gen_id());
+        let inside = LLVMBuildICmp(self.builder, IPredicate::LLVMIntULE, end, len, gen_id());
+        let valid = LLVMBuildAnd(self.builder, ordered, inside, gen_id());
+        self.build_check(valid, expr.span, "slice range %llu..%llu is out of bounds for length %llu", &[start, end, len]);
+
+        let mut indices = [start];
+        let ptr = LLVMBuildInBoundsGEP2(self.builder, elem_type, base, indices.as_mut_ptr(), 1, gen_id());
+        let slice = LLVMBuildInsertValue(self.builder, LLVMGetUndef(_type), ptr, 0, gen_id());
+        let slice = LLVMBuildInsertValue(self.builder, slice, LLVMBuildSub(self.builder, end, start, gen_id()), 1, gen_id());
+        Ok(IGValue::new(slice, _type))
+    }
 
-            let ptr = LLVMBuildStructGEP2(self.builder, value._type, value.value, *index as u32, gen_id());
-            IGValue::new(ptr, value._type)
+    /// Panics unless `index < len`, both are `usize` and `signed` is whether the index was signed before that
+    unsafe fn build_bounds_check(&mut self, index: LLVMValueRef, len: LLVMValueRef, signed: bool, span: Span) {
+        let valid = LLVMBuildICmp(self.builder, IPredicate::LLVMIntULT, index, len, gen_id());
+        let message = if signed {
+            "index out of bounds: the index is %lld but the length is %llu"
         } else {
-            panic!("Unsupported value: {:?}", value);
+            "index out of bounds: the index is %llu but the length is %llu"
+        };
+        self.build_check(valid, span, message, &[index, len]);
+    }
+
+    /// Continues when `condition` holds and panics with `message` otherwise, does nothing when checks are off
+    unsafe fn build_check(&mut self, condition: LLVMValueRef, span: Span, message: &str, args: &[LLVMValueRef]) {
+        if !self.checks {
+            return;
+        }
+
+        let ok = self.create_basic_block("check_ok".into());
+        let failed = self.create_basic_block("check_failed".into());
+        LLVMBuildCondBr(self.builder, condition, ok, failed);
+
+        LLVMPositionBuilderAtEnd(self.builder, failed);
+        self.build_panic(span, message, args);
+
+        LLVMPositionBuilderAtEnd(self.builder, ok);
+    }
+
+    /// Prints where `span` is along with `message`, a `printf` format filled in by `args`, then exits
+    unsafe fn build_panic(&mut self, span: Span, message: &str, args: &[LLVMValueRef]) {
+        let location = match self.diagnostics.file(span.file) {
+            Some(file) => {
+                let (line, col) = file.line_col(span.start);
+                format!("{}:{}:{}", file.name, line + 1, col + 1)
+            },
+            None => "<unknown>".into(),
+        };
+        let format = format!("panic at {}: {}\n", location.replace('%', "%%"), message);
+
+        let i32_type = self.llvm_type(&Ty::Int { bits: 32, signed: true });
+        let mut params = [LLVMPointerType(LLVMIntTypeInContext(self.context, 8), 0)];
+        let printf_type = LLVMFunctionType(i32_type, params.as_mut_ptr(), 1, 1);
+        let printf = self.get_or_add_function("printf".into(), printf_type);
+        let mut values = vec![LLVMBuildGlobalStringPtr(self.builder, get_cstring(format), gen_id())];
+        values.extend_from_slice(args);
+        LLVMBuildCall2(self.builder, printf_type, printf, values.as_mut_ptr(), values.len() as u32, gen_id());
+
+        // `exit` flushes the output, so the message isn't lost when it's piped
+        let mut params = [i32_type];
+        let exit_type = LLVMFunctionType(LLVMVoidTypeInContext(self.context), params.as_mut_ptr(), 1, 0);
+        let exit = self.get_or_add_function("exit".into(), exit_type);
+        let mut values = [LLVMConstInt(i32_type, 101, 0)];
+        LLVMBuildCall2(self.builder, exit_type, exit, values.as_mut_ptr(), 1, get_cstring("".into()));
+        LLVMBuildUnreachable(self.builder);
+    }
+
+    /// Pointer to the value of `expr`, temporaries are given a stack slot of their own
+    unsafe fn resolve_address(&mut self, expr: Expr) -> CompileResult<LLVMValueRef> {
+        if let ExprKind::Symbol(_) | ExprKind::Access { .. } | ExprKind::Index { .. } = expr.kind {
+            return Ok(self.resolve_place(expr)?.value);
         }
 
+        let value = self.resolve_value(expr)?;
+        let alloca = self.build_alloca(value._type, gen_id());
+        LLVMBuildStore(self.builder, value.value, alloca);
+        Ok(alloca)
+    }
+
+    /// Passes `receiver` the way the method takes it, by pointer for `&this` and by value for `this`
+    unsafe fn resolve_receiver(&mut self, receiver: Expr, by_ref: bool) -> CompileResult<LLVMValueRef> {
+        let is_ref = matches!(receiver.ty(), Ty::Ref(_));
+        match (by_ref, is_ref) {
+            (true, false) => self.resolve_address(receiver),
+            (false, true) => {
+                let struct_type = self.llvm_type(&Ty::Struct(receiver.ty().struct_name().unwrap().to_string()));
+                let ptr = self.resolve_value(receiver)?;
+                Ok(LLVMBuildLoad2(self.builder, struct_type, ptr.value, gen_id()))
+            },
+            _ => Ok(self.resolve_value(receiver)?.value),
+        }
+    }
+
+    unsafe fn resolve_value(&mut self, value: Expr) -> CompileResult<IGValue> {
+        let _type = self.llvm_type(value.ty());
+        if let ExprKind::Int(i, _) = value.kind {
+            if value.ty().is_float() {
+                Ok(IGValue::new(LLVMConstReal(_type, i as f64), _type))
+            } else {
+                Ok(IGValue::new(LLVMConstInt(_type, i as u64, 0), _type))
+            }
+        } else if let ExprKind::Char(c) = value.kind {
+            Ok(IGValue::new(LLVMConstInt(_type, c as u64, 0), _type))
+        } else if let ExprKind::Float(f, _) = value.kind {
+            Ok(IGValue::new(LLVMConstReal(_type, f), _type))
+        } else if is_length(&value) {
+            let ExprKind::Access { lhs, .. } = value.kind else {
+                panic!("Expected access expression");
+            };
+            let (_, len) = self.resolve_elements(*lhs)?;
+            Ok(IGValue::new(len, _type))
+        } else if let ExprKind::Slice { .. } = value.kind {
+            self.visit_slice(value)
+        } else if let ExprKind::Symbol(_) | ExprKind::Access { .. } | ExprKind::Index { .. } = value.kind {
+            let ptr = self.resolve_place(value)?;
+            Ok(IGValue::new(
+                LLVMBuildLoad2(self.builder, ptr._type, ptr.value, gen_id()),
+                ptr._type
+            ))
+        } else if let ExprKind::Bool(b) = value.kind {
+            Ok(IGValue::new(LLVMConstInt(_type, b as u64, 0), _type))
+        } else if let ExprKind::Binary { .. } = value.kind {
+            self.visit_binexpr(value)
+        } else if let ExprKind::Prefix { .. } = value.kind {
+            self.visit_prefix(value)
+        } else if let ExprKind::Increment { .. } = value.kind {
+            self.visit_increment(value)
+        } else if let ExprKind::Ternary { .. } = value.kind {
+            self.visit_ternary(value)
+        } else if let ExprKind::String(s) = value.kind.clone() {
+            // Stored with a nul after it, so a literal can be handed to C as is
+            let usize = self.llvm_type(&Ty::from_name("usize").unwrap());
+            let len = LLVMConstInt(usize, s.len() as u64, 0);
+            let bytes = LLVMConstStringInContext(self.context, s.as_ptr() as *const i8, s.len() as u32, 0);
+            let global = LLVMAddGlobal(self.module, LLVMTypeOf(bytes), gen_id_pre("str".into()));
+            LLVMSetInitializer(global, bytes);
+            LLVMSetGlobalConstant(global, 1);
+            LLVMSetLinkage(global, LLVMLinkage::LLVMPrivateLinkage);
+
+            let mut indices = [LLVMConstInt(usize, 0, 0), LLVMConstInt(usize, 0, 0)];
+            let ptr = LLVMBuildInBoundsGEP2(self.builder, LLVMTypeOf(bytes), global, indices.as_mut_ptr(), 2, gen_id());
+            let string = LLVMBuildInsertValue(self.builder, LLVMGetUndef(_type), ptr, 0, gen_id());
+            Ok(IGValue::new(LLVMBuildInsertValue(self.builder, string, len, 1, gen_id()), _type))
+        } else if let ExprKind::Widen(inner) | ExprKind::Cast { expr: inner, .. } = value.kind.clone() {
+            let from = inner.ty().clone();
+            let inner = self.resolve_value(*inner)?;
+            Ok(IGValue::new(self.build_conversion(inner.value, &from, value.ty()), _type))
+        } else if let ExprKind::Call { .. } | ExprKind::MethodCall { .. } = value.kind {
+            Ok(self.visit_call_expr(value)?.expect("Void call was used as a value"))
+        } else if let ExprKind::StructInitialize { name, fields } = value.kind.clone() {
+            let mut _struct = LLVMGetUndef(_type);
+
+            for field in fields.iter() {
+                let StmtKind::StructInitField { name: fname, value } = &field.kind else {
+                    panic!("Expected struct init field");
+                };
+
+                let val = self.resolve_value(*value.clone())?;
+
+                let index = *self.current_scope.resolve_field(format!("{}.{}", name, fname)).expect("Field was not checked");
+                _struct = LLVMBuildInsertValue(self.builder, _struct, val.value, index as u32, gen_id());
+            }
+
+            Ok(IGValue::new(_struct, _type))
+        } else if let ExprKind::Array(elements) = value.kind.clone() {
+            let mut array = LLVMGetUndef(_type);
+            for (i, element) in elements.into_iter().enumerate() {
+                let val = self.resolve_value(element)?;
+                array = LLVMBuildInsertValue(self.builder, array, val.value, i as u32, gen_id());
+            }
+            Ok(IGValue::new(array, _type))
+        } else if let ExprKind::New(inner) = value.kind.clone() {
+            let struct_type = self.llvm_type(inner.ty());
+            let val = self.resolve_value(*inner)?;
+            let ptr = LLVMBuildMalloc(self.builder, struct_type, gen_id_pre("new".into()));
+            LLVMBuildStore(self.builder, val.value, ptr);
+            Ok(IGValue::new(ptr, _type))
+        } else {
+            Err(Diagnostic::error(codes::UNSUPPORTED, "this expression is not supported as a value yet")
+                .with_label(value.span, "unsupported expression"))
+        }
     }
 
     unsafe fn write_ir(&self, output: &Path) {
@@ -679,21 +1476,30 @@ impl Compiler {
     }
 }
 
-fn get_cstring(s: String) -> *mut i8 {
-    let cs = CString::new(s).unwrap();
-    let cv: Vec<u8> = cs.into_bytes_with_nul();
-    let mut tmp: Vec<i8> = cv.into_iter().map(|c| c as i8).collect::<_>();
-    tmp.as_mut_ptr()
+/// Whether `expr` can be evaluated even when its result isn't used, without
+/// side effects or faults such as dividing by zero or reading through a reference.
+/// With `checks` on, integer arithmetic can panic and so isn't speculatable either
+fn is_speculatable(expr: &Expr, checks: bool) -> bool {
+    let can_panic = |op: &Token| checks && !expr.ty().is_float() && matches!(op, Token::Plus | Token::Minus | Token::Multiply | Token::ShiftLeft | Token::ShiftRight);
+    match &expr.kind {
+        ExprKind::Int(..) | ExprKind::Float(..) | ExprKind::Bool(_) | ExprKind::Symbol(_) => true,
+        ExprKind::Widen(inner) | ExprKind::Cast { expr: inner, .. } => is_speculatable(inner, checks),
+        ExprKind::Prefix { op, right } => !can_panic(op) && is_speculatable(right, checks),
+        ExprKind::Binary { left, op, right } => {
+            !matches!(op, Token::Divide | Token::Mod | Token::Power(_) | Token::And | Token::Or)
+                && !can_panic(op) && is_speculatable(left, checks) && is_speculatable(right, checks)
+        },
+        _ => false,
+    }
 }
 
-fn is_program_in_path(program: &str) -> bool {
-    if let Ok(path) = std::env::var("PATH") {
-        for p in path.split(":") {
-            let p_str = format!("{}/{}", p, program);
-            if std::fs::metadata(p_str).is_ok() {
-                return true;
-            }
-        }
-    }
-    false
-}  
\ No newline at end of file
+/// `array.len` or `slice.len`
+fn is_length(expr: &Expr) -> bool {
+    matches!(&expr.kind, ExprKind::Access { lhs, .. } if lhs.ty().element().is_some())
+}
+
+fn get_cstring(s: String) -> *mut i8 {
+    // LLVM copies names and paths it is handed, leaking here keeps the pointer valid until it does
+    CString::new(s).unwrap().into_raw()
+}
+  
\ No newline at end of file
@@ -7,45 +7,39 @@ use super::value::IGValue;
 type SymbolTable = HashMap<String, IGValue>;
 type FieldTable = HashMap<String, usize>;
 
-#[derive(Clone)]
+/// Stack of symbol frames, the first holds the module's declarations and every
+/// block pushes one of its own so inner definitions shadow outer ones until it ends
 pub struct IGScope {
-    pub symbols: SymbolTable,
-    pub fields: FieldTable,
-    parent: Option<Box<Self>>,
-    name: Option<String>,
+    frames: Vec<SymbolTable>,
+    fields: FieldTable,
+}
+
+impl Default for IGScope {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl IGScope {
-    pub fn new(symbols: Option<SymbolTable>, fields: Option<FieldTable>, name: Option<String>, parent: Option<Box<Self>>) -> Self {
-        let _symbols = symbols.unwrap_or_default();
-        let _fields = fields.unwrap_or_default();
+    pub fn new() -> Self {
         Self {
-            symbols: _symbols,
-            fields: _fields,
-            parent,
-            name,
+            frames: vec![SymbolTable::new()],
+            fields: FieldTable::new(),
         }
     }
 
-    pub fn define(&mut self, name: String, value: LLVMValueRef, _type: LLVMTypeRef, mutable: bool, public: bool) -> LLVMValueRef {
-        self.symbols.insert(name, IGValue {
-            _type,
-            value,
-            mutable,
-            public,
-            parent: None,
-        });
+    pub fn push(&mut self) {
+        self.frames.push(SymbolTable::new());
+    }
 
-        value
+    pub fn pop(&mut self) {
+        self.frames.pop();
     }
 
-    pub fn define_struct(&mut self, name: String, value: LLVMValueRef, _type: LLVMTypeRef, mutable: bool, public: bool, parent: String) -> LLVMValueRef {
-        self.symbols.insert(name, IGValue {
+    pub fn define(&mut self, name: String, value: LLVMValueRef, _type: LLVMTypeRef) -> LLVMValueRef {
+        self.frames.last_mut().unwrap().insert(name, IGValue {
             _type,
             value,
-            mutable,
-            public,
-            parent: Some(parent),
         });
 
         value
@@ -55,37 +49,18 @@ impl IGScope {
         self.fields.insert(name, index);
     }
 
-    pub fn define_type(&mut self, name: String, _type: LLVMTypeRef, mutable: bool, public: bool) {
-        self.symbols.insert(name, IGValue {
+    pub fn define_type(&mut self, name: String, _type: LLVMTypeRef) {
+        self.frames.last_mut().unwrap().insert(name, IGValue {
             _type,
             value: unsafe { LLVMConstInt(LLVMIntType(1), 0, 0) },
-            mutable,
-            public,
-            parent: None,
         });
     }
     
     pub fn resolve(&self, name: String) -> Option<&IGValue> {
-        if self.symbols.contains_key(&name) {
-            return Some(self.symbols.get(&name).unwrap());   
-        }
-
-        if let Some(parent) = &self.parent {
-            return parent.resolve(name);
-        }
-
-        None
+        self.frames.iter().rev().find_map(|f| f.get(&name))
     }
 
     pub fn resolve_field(&self, name: String) -> Option<&usize> {
-        if self.fields.contains_key(&name) {
-            return Some(self.fields.get(&name).unwrap());   
-        }
-
-        if let Some(parent) = &self.parent {
-            return parent.resolve_field(name);
-        }
-
-        None
+        self.fields.get(&name)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,41 @@
+// Lexer
+pub const INVALID_TOKEN: &str = "E0001";
+pub const INVALID_LITERAL: &str = "E0002";
+
+// Parser
+pub const UNEXPECTED_TOKEN: &str = "E0100";
+pub const EXPECTED_EXPRESSION: &str = "E0101";
+pub const EXPECTED_TYPE: &str = "E0102";
+pub const EXPECTED_DECLARATION: &str = "E0103";
+pub const INVALID_ASSIGNMENT_TARGET: &str = "E0104";
+pub const INVALID_CALLEE: &str = "E0105";
+pub const INVALID_RECEIVER: &str = "E0106";
+
+// Semantic analysis
+pub const UNRESOLVED_SYMBOL: &str = "E0200";
+pub const UNRESOLVED_TYPE: &str = "E0201";
+pub const UNRESOLVED_FIELD: &str = "E0202";
+pub const REDEFINITION: &str = "E0203";
+pub const IMMUTABLE_ASSIGNMENT: &str = "E0204";
+pub const INVALID_OPERATION: &str = "E0205";
+pub const UNSUPPORTED: &str = "E0206";
+pub const VOID_VALUE: &str = "E0207";
+pub const MISMATCHED_TYPES: &str = "E0208";
+pub const ARGUMENT_COUNT: &str = "E0209";
+pub const MISSING_RETURN: &str = "E0210";
+pub const NOT_CALLABLE: &str = "E0211";
+pub const NOT_A_VALUE: &str = "E0212";
+pub const OUTSIDE_LOOP: &str = "E0213";
+pub const UNRESOLVED_LABEL: &str = "E0214";
+pub const RECURSIVE_TYPE: &str = "E0215";
+pub const LITERAL_OUT_OF_RANGE: &str = "E0216";
+pub const INVALID_CAST: &str = "E0217";
+pub const DUPLICATE_FIELD: &str = "E0218";
+pub const MISSING_FIELD: &str = "E0219";
+pub const INDEX_OUT_OF_BOUNDS: &str = "E0220";
+pub const TYPE_ANNOTATIONS_NEEDED: &str = "E0221";
+
+// Driver
+pub const INCLUDE_NOT_FOUND: &str = "E0300";
+pub const TOOLCHAIN_FAILED: &str = "E0301";
+pub const INCLUDE_CYCLE: &str = "E0302";
@@ -0,0 +1,168 @@
+use std::fmt::Write;
+
+use super::{Diagnostic, Diagnostics, Label};
+
+/// Renders a diagnostic with the offending source lines and carets underneath
+pub fn human(diagnostics: &Diagnostics, diagnostic: &Diagnostic) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}[{}]: {}", diagnostic.severity.as_str(), diagnostic.code, diagnostic.message);
+
+    let mut labels: Vec<&Label> = diagnostic.labels.iter().filter(|l| l.primary).collect();
+    labels.extend(diagnostic.labels.iter().filter(|l| !l.primary));
+
+    let width = labels.iter()
+        .filter_map(|l| diagnostics.file(l.span.file).map(|f| f.line_col(l.span.start).0 + 1))
+        .max()
+        .unwrap_or(0)
+        .to_string()
+        .len();
+    let pad = " ".repeat(width);
+
+    let mut current_file = None;
+    for label in labels {
+        let Some(file) = diagnostics.file(label.span.file) else {
+            continue;
+        };
+
+        let (line, col) = file.line_col(label.span.start);
+        if current_file != Some(label.span.file) {
+            let _ = writeln!(out, "{}--> {}:{}:{}", pad, file.name, line + 1, col + 1);
+            let _ = writeln!(out, "{} |", pad);
+            current_file = Some(label.span.file);
+        }
+
+        let text = file.line(line);
+        let (end_line, end_col) = file.line_col(label.span.end);
+        let end_col = if end_line == line { end_col } else { text.chars().count() };
+        let indent: String = text.chars().take(col).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+        let marker = if label.primary { "^" } else { "-" };
+
+        let _ = writeln!(out, "{:>width$} | {}", line + 1, text, width = width);
+        let _ = writeln!(out, "{} | {}{} {}", pad, indent, marker.repeat(end_col.saturating_sub(col).max(1)), label.message);
+    }
+
+    for note in diagnostic.notes.iter() {
+        let _ = writeln!(out, "{} = note: {}", pad, note);
+    }
+
+    out.push('\n');
+    out
+}
+
+/// Renders a diagnostic as a single line JSON object for editor integrations
+pub fn json(diagnostics: &Diagnostics, diagnostic: &Diagnostic) -> String {
+    let mut out = String::new();
+    let _ = write!(out, "{{\"severity\":{},\"code\":{},\"message\":{}",
+        escape(diagnostic.severity.as_str()), escape(diagnostic.code), escape(&diagnostic.message));
+
+    if let Some(span) = diagnostic.primary_span()
+        && let Some(file) = diagnostics.file(span.file) {
+        let (line, col) = file.line_col(span.start);
+        let _ = write!(out, ",\"file\":{},\"line\":{},\"column\":{}", escape(&file.name), line + 1, col + 1);
+    }
+
+    out.push_str(",\"labels\":[");
+    for (i, label) in diagnostic.labels.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        let _ = write!(out, "{{\"start\":{},\"end\":{},\"message\":{},\"primary\":{}",
+            label.span.start, label.span.end, escape(&label.message), label.primary);
+        if let Some(file) = diagnostics.file(label.span.file) {
+            let (line, col) = file.line_col(label.span.start);
+            let (end_line, end_col) = file.line_col(label.span.end);
+            let _ = write!(out, ",\"file\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+                escape(&file.name), line + 1, col + 1, end_line + 1, end_col + 1);
+        }
+        out.push('}');
+    }
+
+    out.push_str("],\"notes\":[");
+    for (i, note) in diagnostic.notes.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&escape(note));
+    }
+    out.push_str("]}");
+
+    out
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Span;
+
+    fn sample() -> (Diagnostics, Diagnostic) {
+        let mut diagnostics = Diagnostics::new();
+        let file = diagnostics.add_file("main.ig".into(), "let x = 1;\nlet y: bool = x;\n".into());
+        let diagnostic = Diagnostic::error("E0208", "mismatched types")
+            .with_label(Span::new(file, 25, 26), "expected `bool`, found `i32`")
+            .with_secondary(Span::new(file, 4, 5), "defined here")
+            .with_note("a \"note\"\twith escapes");
+        (diagnostics, diagnostic)
+    }
+
+    #[test]
+    fn human_shows_the_source_under_the_message() {
+        let (diagnostics, diagnostic) = sample();
+        assert_eq!(human(&diagnostics, &diagnostic), concat!(
+            "error[E0208]: mismatched types\n",
+            " --> main.ig:2:15\n",
+            "  |\n",
+            "2 | let y: bool = x;\n",
+            "  |               ^ expected `bool`, found `i32`\n",
+            "1 | let x = 1;\n",
+            "  |     - defined here\n",
+            "  = note: a \"note\"\twith escapes\n",
+            "\n",
+        ));
+    }
+
+    #[test]
+    fn human_keeps_tabs_in_the_indent() {
+        let mut diagnostics = Diagnostics::new();
+        let file = diagnostics.add_file("main.ig".into(), "\tfoo(é, bar);".into());
+        let diagnostic = Diagnostic::error("E0200", "cannot find `bar` in this scope")
+            .with_label(Span::new(file, 9, 12), "not found in this scope");
+        let out = human(&diagnostics, &diagnostic);
+        assert!(out.contains(" --> main.ig:1:9\n"), "{}", out);
+        assert!(out.contains("  | \t       ^^^ not found in this scope\n"), "{}", out);
+    }
+
+    #[test]
+    fn json_is_one_escaped_line() {
+        let (diagnostics, diagnostic) = sample();
+        assert_eq!(json(&diagnostics, &diagnostic), concat!(
+            r#"{"severity":"error","code":"E0208","message":"mismatched types","file":"main.ig","line":2,"column":15,"#,
+            r#""labels":[{"start":25,"end":26,"message":"expected `bool`, found `i32`","primary":true,"file":"main.ig","line":2,"column":15,"end_line":2,"end_column":16},"#,
+            r#"{"start":4,"end":5,"message":"defined here","primary":false,"file":"main.ig","line":1,"column":5,"end_line":1,"end_column":6}],"#,
+            r#""notes":["a \"note\"\twith escapes"]}"#,
+        ));
+    }
+
+    #[test]
+    fn escape_control_characters() {
+        assert_eq!(escape("a\\b\n\r\u{1}"), r#""a\\b\n\r\u0001""#);
+    }
+}
@@ -1,20 +1,66 @@
 use logos::Logos;
 
+use crate::diagnostics::{codes, Diagnostic, Diagnostics, FileId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.file, self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// Value of a number literal along with the type it was given, `255u8` has the suffix `u8`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Number<T> {
+    pub value: T,
+    pub suffix: Option<&'static str>,
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for Number<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.value, self.suffix.unwrap_or(""))
+    }
+}
+
+const INT_SUFFIXES: [&str; 10] = ["i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize"];
+const FLOAT_SUFFIXES: [&str; 3] = ["f16", "f32", "f64"];
 
 #[derive(Debug, Logos, PartialEq, Clone)]
-#[logos(skip r"[ \t\n]+")]
+#[logos(skip r"[ \t\r\n]+")]
 #[logos(skip r"\/\/.*")]
 #[logos(error = String)]
 pub enum Token {    
-    #[regex("[0-9]+", |lex| lex.slice().parse::<isize>().unwrap(), priority=1)]
-    Integer(isize),
+    // Everything up to the next symbol is taken so a bad digit or suffix is one error instead of two tokens
+    #[regex(r"[0-9][0-9a-zA-Z_]*", |lex| parse_integer(lex.slice()), priority=2)]
+    Integer(Number<i128>),
 
-    #[regex(r"[0-9]+(\.[0-9]+)", |lex| lex.slice().parse::<f64>().unwrap(), priority=2)]
-    Decimel(f64),
+    #[regex(r"[0-9][0-9_]*\.[0-9][0-9_]*([eE][+-]?[0-9_]+)?[a-zA-Z0-9_]*", |lex| parse_float(lex.slice()), priority=3)]
+    #[regex(r"\.[0-9][0-9_]*([eE][+-]?[0-9_]+)?[a-zA-Z0-9_]*", |lex| parse_float(lex.slice()), priority=3)]
+    #[regex(r"[0-9][0-9_]*[eE][+-]?[0-9_]+[a-zA-Z0-9_]*", |lex| parse_float(lex.slice()), priority=3)]
+    Decimel(Number<f64>),
 
-    #[regex(r#""([^"\\\x00-\x1F]|\\(["\\bnfrt/]|u[a-fA-F0-9]{4}))*""#, |lex| let s = lex.slice().to_owned(); s[1..s.len()-1].to_string() )]
+    #[regex(r#""([^"\\\x00-\x1F]|\\[^\x00-\x1F])*""#, |lex| { let s = lex.slice(); unescape(&s[1..s.len()-1]) })]
     String(String),
 
+    #[regex(r"'([^'\\\x00-\x1F]|\\[^\x00-\x1F])*'", |lex| { let s = lex.slice(); unescape_char(&s[1..s.len()-1]) })]
+    Char(char),
+
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_owned())]
     Identifier(String),
 
@@ -66,6 +112,8 @@ pub enum Token {
     Dot,
     #[token("..")]
     Range,
+    #[token("..=")]
+    RangeInclusive,
 
     #[token(";")]
     Semicolon,
@@ -85,6 +133,22 @@ pub enum Token {
     PlusEquals,
     #[token("-=")]
     MinusEquals,
+    #[token("*=")]
+    MultiplyEquals,
+    #[token("/=")]
+    DivideEquals,
+    #[token("%=")]
+    ModEquals,
+    #[token("&=")]
+    BitAndEquals,
+    #[token("|=")]
+    BitOrEquals,
+    #[token("^=")]
+    BitXorEquals,
+    #[token("<<=")]
+    ShiftLeftEquals,
+    #[token(">>=")]
+    ShiftRightEquals,
 
     #[token("+")]
     Plus,
@@ -97,8 +161,21 @@ pub enum Token {
 
     #[token("%")]
     Mod,
-    #[token("^^")]
-    Power,
+    /// Either spelling, kept so messages show the one that was written
+    #[token("^^", power_spelling)]
+    #[token("**", power_spelling)]
+    Power(&'static str),
+
+    #[token("|")]
+    BitOr,
+    #[token("^")]
+    BitXor,
+    #[token("~")]
+    BitNot,
+    #[token("<<")]
+    ShiftLeft,
+    #[token(">>")]
+    ShiftRight,
     
     #[token("->")]
     Arrow,
@@ -117,6 +194,10 @@ pub enum Token {
     Struct,
     #[token("new")]
     New,
+    #[token("delete")]
+    Delete,
+    #[token("as")]
+    As,
 
     #[token("if")]
     If,
@@ -124,14 +205,22 @@ pub enum Token {
     Else,
     #[token("for")]
     For,
+    #[token("in")]
+    In,
     #[token("while")]
     While,
+    #[token("break")]
+    Break,
+    #[token("continue")]
+    Continue,
 
     #[token("sizeof")]
     Sizeof,
 
     #[token("let")]
     Let,
+    #[token("mut")]
+    Mut,
 
     #[token("linkstatic")]
     LinkStatic,
@@ -141,5 +230,396 @@ pub enum Token {
     #[token("extern")]
     Extern,
 
-    EOF,
-}
\ No newline at end of file
+    Eof,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Integer(v) => write!(f, "integer `{}`", v),
+            Token::Decimel(v) => write!(f, "decimal `{}`", v),
+            Token::String(v) => write!(f, "string \"{}\"", v),
+            Token::Char(v) => write!(f, "character {:?}", v),
+            Token::Identifier(v) => write!(f, "identifier `{}`", v),
+            Token::True => write!(f, "`true`"),
+            Token::False => write!(f, "`false`"),
+            Token::OpenBracket => write!(f, "`[`"),
+            Token::CloseBracket => write!(f, "`]`"),
+            Token::OpenCurly => write!(f, "`{{`"),
+            Token::CloseCurly => write!(f, "`}}`"),
+            Token::OpenParen => write!(f, "`(`"),
+            Token::CloseParen => write!(f, "`)`"),
+            Token::Assignment => write!(f, "`=`"),
+            Token::Equals => write!(f, "`==`"),
+            Token::NotEquals => write!(f, "`!=`"),
+            Token::Less => write!(f, "`<`"),
+            Token::LessOrEqual => write!(f, "`<=`"),
+            Token::Greater => write!(f, "`>`"),
+            Token::GreaterOrEqual => write!(f, "`>=`"),
+            Token::Not => write!(f, "`!`"),
+            Token::Or => write!(f, "`||`"),
+            Token::And => write!(f, "`&&`"),
+            Token::Dot => write!(f, "`.`"),
+            Token::Range => write!(f, "`..`"),
+            Token::RangeInclusive => write!(f, "`..=`"),
+            Token::Semicolon => write!(f, "`;`"),
+            Token::Colon => write!(f, "`:`"),
+            Token::Question => write!(f, "`?`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::PlusPlus => write!(f, "`++`"),
+            Token::MinusMinus => write!(f, "`--`"),
+            Token::PlusEquals => write!(f, "`+=`"),
+            Token::MinusEquals => write!(f, "`-=`"),
+            Token::MultiplyEquals => write!(f, "`*=`"),
+            Token::DivideEquals => write!(f, "`/=`"),
+            Token::ModEquals => write!(f, "`%=`"),
+            Token::BitAndEquals => write!(f, "`&=`"),
+            Token::BitOrEquals => write!(f, "`|=`"),
+            Token::BitXorEquals => write!(f, "`^=`"),
+            Token::ShiftLeftEquals => write!(f, "`<<=`"),
+            Token::ShiftRightEquals => write!(f, "`>>=`"),
+            Token::Plus => write!(f, "`+`"),
+            Token::Minus => write!(f, "`-`"),
+            Token::Multiply => write!(f, "`*`"),
+            Token::Divide => write!(f, "`/`"),
+            Token::Mod => write!(f, "`%`"),
+            Token::Power(v) => write!(f, "`{}`", v),
+            Token::BitOr => write!(f, "`|`"),
+            Token::BitXor => write!(f, "`^`"),
+            Token::BitNot => write!(f, "`~`"),
+            Token::ShiftLeft => write!(f, "`<<`"),
+            Token::ShiftRight => write!(f, "`>>`"),
+            Token::Arrow => write!(f, "`->`"),
+            Token::Reference => write!(f, "`&`"),
+            Token::Include => write!(f, "`include`"),
+            Token::Subroutine => write!(f, "`sub`"),
+            Token::Return => write!(f, "`return`"),
+            Token::Struct => write!(f, "`struct`"),
+            Token::New => write!(f, "`new`"),
+            Token::Delete => write!(f, "`delete`"),
+            Token::As => write!(f, "`as`"),
+            Token::If => write!(f, "`if`"),
+            Token::Else => write!(f, "`else`"),
+            Token::For => write!(f, "`for`"),
+            Token::In => write!(f, "`in`"),
+            Token::While => write!(f, "`while`"),
+            Token::Break => write!(f, "`break`"),
+            Token::Continue => write!(f, "`continue`"),
+            Token::Sizeof => write!(f, "`sizeof`"),
+            Token::Let => write!(f, "`let`"),
+            Token::Mut => write!(f, "`mut`"),
+            Token::LinkStatic => write!(f, "`linkstatic`"),
+            Token::LinkLib => write!(f, "`linklib`"),
+            Token::Extern => write!(f, "`extern`"),
+            Token::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+fn power_spelling(lex: &mut logos::Lexer<Token>) -> &'static str {
+    if lex.slice() == "**" { "**" } else { "^^" }
+}
+
+/// Reads an integer literal such as `1_000`, `0xFF` or `255u8`. A float suffix is allowed
+/// on decimal digits so `1f32` works, the value is checked against the type later
+fn parse_integer(slice: &str) -> Result<Number<i128>, String> {
+    let (radix, name, body) = match slice.get(..2) {
+        Some("0x") => (16, "hexadecimal", &slice[2..]),
+        Some("0o") => (8, "octal", &slice[2..]),
+        Some("0b") => (2, "binary", &slice[2..]),
+        _ => (10, "decimal", slice),
+    };
+
+    // Hex digits include `a` to `f`, so only letters that aren't digits can start the suffix
+    let end = body.find(|c: char| !(c == '_' || if radix == 16 { c.is_ascii_hexdigit() } else { c.is_ascii_digit() }))
+        .unwrap_or(body.len());
+    let (digits, suffix) = body.split_at(end);
+    let digits = digits.replace('_', "");
+    if digits.is_empty() {
+        return Err(format!("{} literal has no digits", name));
+    }
+    if let Some(bad) = digits.chars().find(|c| !c.is_digit(radix)) {
+        return Err(format!("invalid digit `{}` in {} literal", bad, name));
+    }
+
+    let suffix = match suffix {
+        "" => None,
+        _ if radix == 10 => Some(find_suffix(suffix, &[&INT_SUFFIXES, &FLOAT_SUFFIXES])?),
+        _ if FLOAT_SUFFIXES.contains(&suffix) => return Err(format!("{} literals can't be floats", name)),
+        _ => Some(find_suffix(suffix, &[&INT_SUFFIXES])?),
+    };
+
+    // Nothing is wider than 64 bits, anything larger couldn't be used anyway
+    match u64::from_str_radix(&digits, radix) {
+        Ok(value) => Ok(Number { value: value as i128, suffix }),
+        Err(_) => Err(format!("integer literal is too large, the largest is {}", u64::MAX)),
+    }
+}
+
+/// Reads a float literal such as `1.5`, `.5`, `1e-9` or `1.0f64`
+fn parse_float(slice: &str) -> Result<Number<f64>, String> {
+    let mut end = slice.find(|c: char| !(c.is_ascii_digit() || c == '_' || c == '.')).unwrap_or(slice.len());
+    let rest = &slice[end..];
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let unsigned = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '_') {
+            let digits = unsigned.find(|c: char| !(c.is_ascii_digit() || c == '_')).unwrap_or(unsigned.len());
+            end = slice.len() - unsigned.len() + digits;
+        }
+    }
+
+    let (number, suffix) = slice.split_at(end);
+    let suffix = match suffix {
+        "" => None,
+        _ if INT_SUFFIXES.contains(&suffix) => return Err(format!("float literals can't have the integer suffix `{}`", suffix)),
+        _ => Some(find_suffix(suffix, &[&FLOAT_SUFFIXES])?),
+    };
+
+    let number = number.replace('_', "");
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(Number { value, suffix }),
+        Ok(_) => Err("float literal is too large, the largest is `f64::MAX`".into()),
+        Err(_) => Err(format!("invalid float literal `{}`", slice)),
+    }
+}
+
+fn find_suffix(suffix: &str, allowed: &[&[&'static str]]) -> Result<&'static str, String> {
+    allowed.iter().flat_map(|list| list.iter()).find(|s| **s == suffix).copied()
+        .ok_or_else(|| format!("invalid suffix `{}` for number literal", suffix))
+}
+
+/// Replaces the escape sequences in the body of a string literal with what they stand for
+fn unescape(body: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        let escape = chars.next().expect("Lexer matched a trailing backslash");
+        out.push(match escape {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'b' => '\x08',
+            'f' => '\x0C',
+            '\\' | '"' | '\'' | '/' => escape,
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 && byte <= 0x7F => byte as char,
+                    Ok(_) if digits.len() == 2 => return Err(format!("`\\x{}` is not ASCII, use `\\u{{...}}` for other characters", digits)),
+                    _ => return Err("`\\x` must be followed by two hex digits".into()),
+                }
+            },
+            'u' => {
+                // Either `\u{1F525}` or the fixed width `\u00E9`
+                let rest = chars.as_str();
+                let digits = if let Some(braced) = rest.strip_prefix('{') {
+                    let Some(end) = braced.find('}') else {
+                        return Err("unterminated `\\u{...}` escape".into());
+                    };
+                    chars = braced[end + 1..].chars();
+                    &braced[..end]
+                } else {
+                    let Some(digits) = rest.get(..4).filter(|d| d.chars().all(|c| c.is_ascii_hexdigit())) else {
+                        return Err("`\\u` must be followed by four hex digits or `{...}`".into());
+                    };
+                    chars = rest[4..].chars();
+                    digits
+                };
+
+                let scalar = (1..=6).contains(&digits.len())
+                    .then(|| u32::from_str_radix(digits, 16).ok())
+                    .flatten();
+                match scalar.and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return Err(format!("`\\u{{{}}}` is not a valid unicode escape", digits)),
+                }
+            },
+            _ => return Err(format!("unknown escape `\\{}`", escape)),
+        });
+    }
+    Ok(out)
+}
+
+/// The one character a character literal holds
+fn unescape_char(body: &str) -> Result<char, String> {
+    let value = unescape(body)?;
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        (None, _) => Err("empty character literal".into()),
+        _ => Err("character literal holds more than one character, use `\"` for strings".into()),
+    }
+}
+
+/// Lexes `source`, reporting and skipping every invalid token so parsing can still find later errors
+pub fn tokenize(source: &str, file: FileId, diagnostics: &mut Diagnostics) -> Vec<SpannedToken> {
+    let mut lexer = Token::lexer(source);
+    let mut tokens = vec![];
+
+    while let Some(t) = lexer.next() {
+        let range = lexer.span();
+        let span = Span::new(file, range.start, range.end);
+        match t {
+            Ok(token) => tokens.push(SpannedToken { token, span }),
+            Err(e) if e.is_empty() => {
+                diagnostics.report(Diagnostic::error(codes::INVALID_TOKEN, format!("invalid token `{}`", lexer.slice()))
+                    .with_label(span, "not recognized by the lexer"));
+            },
+            Err(e) => {
+                diagnostics.report(Diagnostic::error(codes::INVALID_LITERAL, e)
+                    .with_label(span, "in this literal"));
+
+                // The literal is still there, parsing goes on as if it were fine
+                if lexer.slice().starts_with('"') {
+                    tokens.push(SpannedToken { token: Token::String(String::new()), span });
+                } else if lexer.slice().starts_with('\'') {
+                    tokens.push(SpannedToken { token: Token::Char('\0'), span });
+                } else {
+                    tokens.push(SpannedToken { token: Token::Integer(Number { value: 0, suffix: None }), span });
+                }
+            },
+        }
+    }
+
+    tokens.push(SpannedToken { token: Token::Eof, span: Span::new(file, source.len(), source.len()) });
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> (Vec<Token>, Diagnostics) {
+        let mut diagnostics = Diagnostics::new();
+        let file = diagnostics.add_file("test.ig".into(), source.into());
+        let tokens = tokenize(source, file, &mut diagnostics).into_iter().map(|t| t.token).collect();
+        (tokens, diagnostics)
+    }
+
+    fn single(source: &str) -> Token {
+        let (tokens, diagnostics) = lex(source);
+        assert!(!diagnostics.has_errors(), "`{}` failed to lex: {:?}", source, diagnostics.diagnostics());
+        assert_eq!(tokens.len(), 2, "`{}` lexed to {:?}", source, tokens);
+        tokens[0].clone()
+    }
+
+    fn error(source: &str) -> String {
+        let (_, diagnostics) = lex(source);
+        let errors = diagnostics.diagnostics();
+        assert_eq!(errors.len(), 1, "`{}` reported {:?}", source, errors);
+        errors[0].message.clone()
+    }
+
+    fn int(value: i128, suffix: Option<&'static str>) -> Token {
+        Token::Integer(Number { value, suffix })
+    }
+
+    #[test]
+    fn tokens_carry_their_byte_range() {
+        let source = "let x = 5; // five\nfoo(\"hi\")";
+        let tokens = tokenize(source, 0, &mut Diagnostics::new());
+        let spans: Vec<&str> = tokens.iter().map(|t| &source[t.span.start..t.span.end]).collect();
+        assert_eq!(spans, ["let", "x", "=", "5", ";", "foo", "(", "\"hi\"", ")", ""]);
+        assert_eq!(tokens.last().unwrap().span, Span::new(0, source.len(), source.len()));
+    }
+
+    #[test]
+    fn invalid_tokens_are_reported_and_skipped() {
+        let mut diagnostics = Diagnostics::new();
+        let tokens = tokenize("let x = $; y = #;", 3, &mut diagnostics);
+        let errors: Vec<_> = diagnostics.diagnostics().iter().map(|d| (d.code, d.message.as_str(), d.primary_span())).collect();
+        assert_eq!(errors, [
+            (codes::INVALID_TOKEN, "invalid token `$`", Some(Span::new(3, 8, 9))),
+            (codes::INVALID_TOKEN, "invalid token `#`", Some(Span::new(3, 15, 16))),
+        ]);
+        assert_eq!(tokens.len(), 8);
+    }
+
+    #[test]
+    fn span_to_covers_both() {
+        assert_eq!(Span::new(0, 4, 6).to(Span::new(0, 1, 2)), Span::new(0, 1, 6));
+    }
+
+    #[test]
+    fn integer_literals() {
+        assert_eq!(single("1_000_000"), int(1_000_000, None));
+        assert_eq!(single("0xFF"), int(255, None));
+        assert_eq!(single("0xffu8"), int(255, Some("u8")));
+        assert_eq!(single("0b1010"), int(10, None));
+        assert_eq!(single("0o17"), int(15, None));
+        assert_eq!(single("255u8"), int(255, Some("u8")));
+        assert_eq!(single("3f32"), int(3, Some("f32")));
+        assert_eq!(single("18446744073709551615"), int(u64::MAX as i128, None));
+    }
+
+    #[test]
+    fn invalid_integer_literals() {
+        assert_eq!(error("0x"), "hexadecimal literal has no digits");
+        assert_eq!(error("0b102"), "invalid digit `2` in binary literal");
+        assert_eq!(error("12u7"), "invalid suffix `u7` for number literal");
+        assert_eq!(single("0xFFf32"), int(0xFFF32, None));
+        assert_eq!(error("0b1f32"), "binary literals can't be floats");
+        assert!(error("18446744073709551616").starts_with("integer literal is too large"));
+    }
+
+    #[test]
+    fn float_literals() {
+        assert_eq!(single("1.5"), Token::Decimel(Number { value: 1.5, suffix: None }));
+        assert_eq!(single(".5f64"), Token::Decimel(Number { value: 0.5, suffix: Some("f64") }));
+        assert_eq!(single("1.5e-9"), Token::Decimel(Number { value: 1.5e-9, suffix: None }));
+        assert_eq!(single("2E+3_0"), Token::Decimel(Number { value: 2e30, suffix: None }));
+        assert_eq!(error("1.5u8"), "float literals can't have the integer suffix `u8`");
+        assert!(error("1e999").starts_with("float literal is too large"));
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(single(r#""a\tb\n""#), Token::String("a\tb\n".into()));
+        assert_eq!(single(r#""\"\\\'\0""#), Token::String("\"\\'\0".into()));
+        assert_eq!(single(r#""\x41""#), Token::String("A".into()));
+        assert_eq!(single(r#""caf\u00E9""#), Token::String("café".into()));
+        assert_eq!(single(r#""\u{1F525}!""#), Token::String("🔥!".into()));
+    }
+
+    #[test]
+    fn invalid_string_escapes() {
+        assert_eq!(error(r#""\q""#), "unknown escape `\\q`");
+        assert_eq!(error(r#""\x4""#), "`\\x` must be followed by two hex digits");
+        assert!(error(r#""\xFF""#).contains("is not ASCII"));
+        assert_eq!(error(r#""\u41""#), "`\\u` must be followed by four hex digits or `{...}`");
+        assert_eq!(error(r#""\u00G1""#), "`\\u` must be followed by four hex digits or `{...}`");
+        assert_eq!(error(r#""\u{110000}""#), "`\\u{110000}` is not a valid unicode escape");
+        assert_eq!(error(r#""\u{D800}""#), "`\\u{D800}` is not a valid unicode escape");
+        assert_eq!(error(r#""\u{41""#), "unterminated `\\u{...}` escape");
+    }
+
+    #[test]
+    fn invalid_literal_keeps_its_place() {
+        let (tokens, diagnostics) = lex(r#"x = "\q";"#);
+        assert_eq!(diagnostics.error_count(), 1);
+        assert_eq!(tokens[2], Token::String(String::new()));
+        assert_eq!(tokens[3], Token::Semicolon);
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(single("'a'"), Token::Char('a'));
+        assert_eq!(single(r"'\n'"), Token::Char('\n'));
+        assert_eq!(single(r"'\x41'"), Token::Char('A'));
+        assert_eq!(single(r"'\u{1F525}'"), Token::Char('🔥'));
+        assert_eq!(error("''"), "empty character literal");
+        assert!(error("'ab'").starts_with("character literal holds more than one character"));
+    }
+
+    #[test]
+    fn power_is_shown_as_written() {
+        assert_eq!(single("**").to_string(), "`**`");
+        assert_eq!(single("^^").to_string(), "`^^`");
+    }
+}
@@ -1,49 +1,63 @@
 mod lexer;
 mod parser;
+mod diagnostics;
+mod checker;
+mod ty;
 pub mod compiler;
 
-use std::{env::args, path::Path};
+use std::{env::args, path::Path, process};
 
+use checker::Checker;
 use compiler::Compiler;
-use lexer::Token;
-use logos::Logos;
+use diagnostics::{Diagnostics, OutputFormat};
 use parser::Parser;
 
 fn main() {
-    let args = args().skip(1).collect::<Vec<_>>();
+    let mut format = OutputFormat::Human;
+    let mut checks = true;
+    let mut positional = vec![];
+
+    for arg in args().skip(1) {
+        match arg.as_str() {
+            "--error-format=json" => format = OutputFormat::Json,
+            "--error-format=human" => format = OutputFormat::Human,
+            "--release" => checks = false,
+            _ => positional.push(arg),
+        }
+    }
 
-    if args.len() != 2 {
-        eprintln!("Usage: ignis <input>");
-        return;
+    if positional.len() != 2 {
+        eprintln!("Usage: ignis <input> <output> [--error-format=human|json] [--release]");
+        process::exit(2);
     }
 
-    let mut iter = args.iter();
+    let mut iter = positional.iter();
 
-    
-    let source = std::fs::read_to_string(iter.next().unwrap()).expect("Failed to find file");
+    let input = iter.next().unwrap();
+    let source = match std::fs::read_to_string(input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: failed to read {}: {}", input, e);
+            process::exit(1);
+        },
+    };
     let output = iter.next().unwrap();
 
-    let lexer = lexer::Token::lexer(&source);
-    let mut tokens = vec![];
-
-    for t in lexer {
-        match t {
-            Ok(tok) => tokens.push(tok),
-            Err(e) => {
-                if !e.is_empty() {
-                    println!("Invalid token: {}", e);
-                    return;
-                }
-            },
-        }
+    let mut diagnostics = Diagnostics::new();
+    let file = diagnostics.add_file(input.clone(), source.clone());
+
+    let tokens = lexer::tokenize(&source, file, &mut diagnostics);
+    let mut ast = Parser::parse(tokens, &mut diagnostics);
+    if !diagnostics.has_errors() {
+        Checker::check(&mut ast, input, vec![], None, &mut diagnostics);
     }
 
-    tokens.push(Token::EOF);
-    let ast = Parser::parse(tokens);
-    println!("{:#?}", ast);
-    Compiler::compile(Path::new(output), ast, vec![], None, false);
-}
+    if !diagnostics.has_errors() {
+        Compiler::compile(Path::new(output), ast, false, checks, &mut diagnostics);
+    }
 
-fn mnt_to_string(bytes: &[i8]) -> String {
-    unsafe { std::str::from_utf8_unchecked(std::mem::transmute(bytes)) }.to_string()
- }
\ No newline at end of file
+    diagnostics.emit(format);
+    if diagnostics.has_errors() {
+        process::exit(1);
+    }
+}
@@ -1,14 +1,18 @@
-use crate::lexer::Token;
+use crate::{lexer::{Span, Token}, ty::Ty};
 
 
 #[derive(Debug, Clone)]
-pub enum Expr {
-    Int(isize),
+pub enum ExprKind {
+    /// The type is set when the literal has a suffix such as `255u8`
+    Int(i128, Option<Ty>),
 
-    Float(f64),
+    Float(f64, Option<Ty>),
 
     String(String),
 
+    /// A unicode scalar value, becomes an integer when the context expects one
+    Char(char),
+
     Symbol(String),
 
     Bool(bool),
@@ -24,40 +28,107 @@ pub enum Expr {
         right: Box<Expr>,
     },
 
+    /// `condition ? then : _else`
+    Ternary {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        _else: Box<Expr>,
+    },
+
+    /// `++x`, `--x`, `x++` or `x--`
+    Increment {
+        target: Box<Expr>,
+        decrement: bool,
+        postfix: bool,
+    },
+
+    /// `[a, b, c]`
     Array(Vec<Expr>),
 
+    /// `target[index]`
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+    },
+
+    /// `target[start..end]`, either bound can be left out to go from the start or to the end
+    Slice {
+        target: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    },
+
     Call {
         name: String,
         args: Vec<Expr>,
     },
 
+    MethodCall {
+        receiver: Box<Expr>,
+        name: String,
+        args: Vec<Expr>,
+
+        /// Filled in by the checker, whether the method takes `&this`
+        by_ref: bool,
+    },
+
     Assignment {
         assignee: Box<Expr>,
         right: Box<Expr>,
     },
 
+    /// `assignee op= right`, where `op` is the binary operator such as `Token::Plus` for `+=`
+    CompoundAssignment {
+        assignee: Box<Expr>,
+        op: Token,
+        right: Box<Expr>,
+    },
+
+    /// `Name { field: value, ... }`, a struct value on the stack
     StructInitialize {
         name: String,
 
         fields: Vec<Stmt>,
     },
 
+    /// `new Name { ... }`, allocates the struct on the heap and gives a reference to it
+    New(Box<Expr>),
+
     Access {
         lhs: Box<Expr>,
         rhs: Box<Expr>
     },
 
+    Cast {
+        expr: Box<Expr>,
+        target: Box<Type>,
+    },
+
+    /// `[start..end]`, `[start..=end]` or `[start..end : step]`, only valid as the thing a `for` iterates over
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+        step: Option<Box<Expr>>,
+    },
+
+    /// Inserted by the checker where a number is implicitly converted to a wider type,
+    /// the inner expression keeps its own type
+    Widen(Box<Expr>),
+
     StmtHack(Stmt),
 }
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub enum StmtKind {
     Block(Vec<Stmt>),
 
     Expression(Box<Expr>),
 
+    /// `let` for an immutable binding or `mut` for a mutable one
     VariableDeclaration {
         name: String,
+        mutable: bool,
 
         explicit_type: Option<Box<Type>>,
 
@@ -73,6 +144,22 @@ pub enum Stmt {
     While {
         condition: Box<Expr>,
         body: Box<Stmt>,
+        label: Option<String>,
+    },
+
+    For {
+        variable: String,
+        iterable: Box<Expr>,
+        body: Box<Stmt>,
+        label: Option<String>,
+    },
+
+    Break {
+        label: Option<String>,
+    },
+
+    Continue {
+        label: Option<String>,
     },
 
     Link {
@@ -85,7 +172,13 @@ pub enum Stmt {
         _type: Box<Type>,
     },
 
+    /// `return;` leaves a `void` function without a value
     Return {
+        value: Option<Box<Expr>>,
+    },
+
+    /// Frees what a `new` expression allocated
+    Delete {
         value: Box<Expr>,
     },
 
@@ -118,14 +211,87 @@ pub enum Stmt {
 
     Include {
         path: String,
+
+        /// Filled in by the checker with the parsed and checked file
+        module: Option<Box<IncludedModule>>,
     },
 }
 
 #[derive(Debug, Clone)]
-pub enum Type {
+pub enum TypeKind {
     Symbol(String),
 
-    Ref(Box<Self>),
+    Ref(Box<Type>),
+
+    /// `[len]T`
+    Array {
+        len: usize,
+        inner: Box<Type>,
+    },
+
+    /// `[]T`, or `[]mut T` when the elements can be written through it
+    Slice {
+        inner: Box<Type>,
+        mutable: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct IncludedModule {
+    pub path: String,
+    pub ast: Stmt,
+
+    /// False when the same file was already included elsewhere in the program,
+    /// its object is then only linked once from that first include
+    pub compile: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
 
-    Array(Box<Self>),
-}
\ No newline at end of file
+    /// Filled in by the checker
+    pub ty: Option<Ty>,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span, ty: None }
+    }
+
+    pub fn ty(&self) -> &Ty {
+        self.ty.as_ref().expect("Expression was not type checked")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Type {
+    pub kind: TypeKind,
+    pub span: Span,
+
+    /// Filled in by the checker
+    pub ty: Option<Ty>,
+}
+
+impl Type {
+    pub fn new(kind: TypeKind, span: Span) -> Self {
+        Self { kind, span, ty: None }
+    }
+
+    pub fn ty(&self) -> &Ty {
+        self.ty.as_ref().expect("Type was not resolved")
+    }
+}
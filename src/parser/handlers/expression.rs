@@ -1,178 +1,340 @@
-use crate::{lexer::Token, parser::{ast::{Expr, Stmt}, pratt::BindingPower, Parser}};
-
-use super::statement::*;
-
-pub fn parse_expression(parser: &mut Parser, bp: BindingPower) -> Expr {
-    let nud_fn = parser.get_nud(parser.current()).unwrap_or_else(|| panic!("NUD FUNCTION DOESNT EXIST FOR TOKEN {:?}", parser.current()));
-    let mut left = nud_fn(parser);
-    loop {
-        if let Some(bp_) = parser.get_bp(parser.current()) {
-            if *bp_ as usize <= bp as usize {
-                break;
-            }
-            let led_fn = parser.get_led(parser.current()).unwrap_or_else(|| panic!("LED FUNCTION DOESNT EXIST FOR TOKEN {:?}", parser.current()));
-            left = led_fn(parser, left, bp);
-            
-        } else {
+use crate::{diagnostics::{codes, Diagnostic}, lexer::Token, parser::{ast::{Expr, ExprKind, Stmt, StmtKind}, pratt::BindingPower, ParseResult, Parser}, ty::Ty};
+
+use super::{statement::*, types::*};
+
+pub fn parse_expression(parser: &mut Parser, bp: BindingPower) -> ParseResult<Expr> {
+    let Some(nud_fn) = parser.get_nud(parser.current()) else {
+        return Err(parser.error(codes::EXPECTED_EXPRESSION, &format!("expected expression but found {}", parser.current()), "expected expression"));
+    };
+    let mut left = nud_fn(parser)?;
+    while let Some(bp_) = parser.get_bp(parser.current()) {
+        if *bp_ as usize <= bp as usize {
             break;
         }
+        let Some(led_fn) = parser.get_led(parser.current()) else {
+            return Err(parser.error(codes::UNEXPECTED_TOKEN, &format!("unexpected {} in expression", parser.current()), "not valid here"));
+        };
+        left = led_fn(parser, left, bp)?;
     }
-    left
+    Ok(left)
 }
 
-pub fn parse_binary_expression(parser: &mut Parser, left: Expr, bp: BindingPower) -> Expr {
+pub fn parse_binary_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
     let op = parser.advance().clone();
-    let right = parse_expression(parser, bp);
 
-    Expr::Binary { left: Box::new(left), op, right: Box::new(right) }
+    // The right side only takes operators that bind tighter, which makes everything left associative
+    // except exponentiation, where `a ^^ b ^^ c` is `a ^^ (b ^^ c)`
+    let bp = match op {
+        Token::Power(_) => BindingPower::Unary,
+        _ => *parser.get_bp(&op).expect("Binary operator has no binding power"),
+    };
+    let right = parse_expression(parser, bp)?;
+
+    let span = left.span.to(right.span);
+    Ok(Expr::new(ExprKind::Binary { left: Box::new(left), op, right: Box::new(right) }, span))
 }
 
-pub fn parse_primary_expression(parser: &mut Parser) -> Expr {
+pub fn parse_primary_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    if let Token::Identifier(_) = parser.current()
+        && parser.struct_literals && matches!(parser.peek(1), Token::OpenCurly) {
+        return parse_struct_literal(parser);
+    }
+
     let v = match parser.current() {
-        Token::Integer(v) => Expr::Int(*v),
-        Token::Decimel(v) => Expr::Float(*v),
-        Token::String(v) => Expr::String(v.into()),
-        Token::Identifier(v) => Expr::Symbol(v.into()),
-        _ => panic!("Failed to parse primary expression {:?}", parser.current())
+        Token::Integer(v) => ExprKind::Int(v.value, v.suffix.and_then(Ty::from_name)),
+        Token::Decimel(v) => ExprKind::Float(v.value, v.suffix.and_then(Ty::from_name)),
+        Token::String(v) => ExprKind::String(v.into()),
+        Token::Char(v) => ExprKind::Char(*v),
+        Token::Identifier(v) => ExprKind::Symbol(v.into()),
+        _ => return Err(parser.error(codes::EXPECTED_EXPRESSION, &format!("expected expression but found {}", parser.current()), "expected expression")),
     };
 
     parser.advance();
-    v
+    Ok(Expr::new(v, parser.last_span()))
 }
 
-pub fn parse_bool_expression(parser: &mut Parser) -> Expr {
-    let v = Expr::Bool(parser.is_current_kind(Token::True));
+pub fn parse_bool_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    let v = ExprKind::Bool(parser.is_current_kind(Token::True));
     parser.advance();
-    v
+    Ok(Expr::new(v, parser.last_span()))
 }
 
-pub fn parse_arrow_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> Expr {
-    let Expr::Symbol(symbol) = left else {
-        panic!("LHS of arrow MUST be an identifer, got {:?}", left);
-    }; 
+pub fn parse_arrow_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
+    let ExprKind::Symbol(symbol) = left.kind else {
+        return Err(Diagnostic::error(codes::EXPECTED_DECLARATION, "left hand side of `->` must be an identifier")
+            .with_label(left.span, "expected a declaration name"));
+    };
 
-    parser.expect(Token::Arrow);
+    parser.expect(Token::Arrow)?;
 
-    match parser.current() {
+    let stmt = match parser.current() {
         Token::Subroutine => {
             parser.advance();
-            Expr::StmtHack(parse_function_declaration(parser, symbol))
+            parse_function_declaration(parser, symbol, left.span)?
         }
         Token::Struct => {
             parser.advance();
-            Expr::StmtHack(parse_struct_declaration(parser, symbol))
+            parse_struct_declaration(parser, symbol, left.span)?
         }
         Token::Extern => {
             parser.advance();
-            Expr::StmtHack(parse_extern(parser, symbol))
+            parse_extern(parser, symbol, left.span)?
         }
-        _ => panic!("Expected high level declaration with arrow but got {:?}", parser.current()),
-    }
+        _ => return Err(parser.error(
+            codes::EXPECTED_DECLARATION,
+            &format!("expected `sub`, `struct` or `extern` after `->` but found {}", parser.current()),
+            "expected declaration",
+        )),
+    };
+
+    let span = stmt.span;
+    Ok(Expr::new(ExprKind::StmtHack(stmt), span))
 }
 
-pub fn parse_grouped_expression(parser: &mut Parser) -> Expr {
+pub fn parse_grouped_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
     parser.advance();
 
+    // Parentheses make it clear where a struct literal ends, so they're allowed again inside
+    let struct_literals = std::mem::replace(&mut parser.struct_literals, true);
     let expr = parse_expression(parser, BindingPower::Default);
-    parser.expect(Token::CloseParen);
+    parser.struct_literals = struct_literals;
+    let mut expr = expr?;
+    parser.expect(Token::CloseParen)?;
+
+    expr.span = parser.span_from(start);
+    Ok(expr)
+}
+
+pub fn parse_cast_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
+    parser.advance();
 
-    expr
+    let target = parse_type(parser, BindingPower::Default)?;
+    let span = left.span.to(target.span);
+    Ok(Expr::new(ExprKind::Cast { expr: Box::new(left), target: Box::new(target) }, span))
 }
 
-pub fn parse_assignment_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> Expr {
+pub fn parse_assignment_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
     parser.advance();
 
-    Expr::Assignment { assignee: Box::new(left), right: Box::new(parse_expression(parser, BindingPower::Default)) }
+    let right = parse_expression(parser, BindingPower::Default)?;
+    let span = left.span.to(right.span);
+    Ok(Expr::new(ExprKind::Assignment { assignee: Box::new(left), right: Box::new(right) }, span))
 }
 
-pub fn parse_prefix_expression(parser: &mut Parser) -> Expr {
+pub fn parse_ternary_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
+    parser.advance();
+
+    let then = parse_expression(parser, BindingPower::Default)?;
+    parser.expect(Token::Colon)?;
+    // Parsed below the ternary's own power so `a ? b : c ? d : e` nests to the right
+    let _else = parse_expression(parser, BindingPower::Assignment)?;
+
+    let span = left.span.to(_else.span);
+    Ok(Expr::new(ExprKind::Ternary { condition: Box::new(left), then: Box::new(then), _else: Box::new(_else) }, span))
+}
+
+pub fn parse_prefix_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
     let op = parser.advance().clone();
 
-    let nud_fn = parser.get_nud(parser.current()).unwrap_or_else(|| panic!("NUD FUNCTION DOESNT EXIST FOR TOKEN {:?}", parser.current()));
-    let right = nud_fn(parser);
+    if parser.get_nud(parser.current()).is_none() {
+        return Err(parser.error(codes::EXPECTED_EXPRESSION, &format!("expected expression after {} but found {}", op, parser.current()), "expected expression"));
+    }
+    // `-x ^^ 2` is `-(x ^^ 2)` while `-x * 2` and `-x as T` apply the operator first
+    let right = parse_expression(parser, BindingPower::Unary)?;
+
+    let span = start.to(right.span);
+    // Negative literals are folded so their range is checked with the sign
+    match (&op, &right.kind) {
+        (Token::Minus, ExprKind::Int(v, suffix)) => return Ok(Expr::new(ExprKind::Int(-v, suffix.clone()), span)),
+        (Token::Minus, ExprKind::Float(v, suffix)) => return Ok(Expr::new(ExprKind::Float(-v, suffix.clone()), span)),
+        _ => {},
+    }
+
+    Ok(Expr::new(ExprKind::Prefix { op, right: Box::new(right) }, span))
+}
+
+pub fn parse_prefix_increment_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
+    let decrement = matches!(parser.advance(), Token::MinusMinus);
+
+    let target = parse_expression(parser, BindingPower::Unary)?;
+    let span = start.to(target.span);
+    Ok(Expr::new(ExprKind::Increment { target: Box::new(target), decrement, postfix: false }, span))
+}
+
+pub fn parse_postfix_increment_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
+    let decrement = matches!(parser.advance(), Token::MinusMinus);
 
-    Expr::Prefix { op, right: Box::new(right) }
+    let span = left.span.to(parser.last_span());
+    Ok(Expr::new(ExprKind::Increment { target: Box::new(left), decrement, postfix: true }, span))
 }
 
-pub fn parse_call_expression(parser: &mut Parser, left: Expr, bp: BindingPower) -> Expr {
-    let Expr::Symbol(callee) = left else {
-        panic!("Expected symbol on the left of a function call but got {:?}", left);
-    }; 
+pub fn parse_call_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
+    let start = left.span;
+    let (receiver, name) = match left.kind {
+        ExprKind::Symbol(name) => (None, name),
+        ExprKind::Access { lhs, rhs } => {
+            let ExprKind::Symbol(method) = rhs.kind else {
+                unreachable!("the parser only builds member accesses with a field name on the right");
+            };
+            (Some(lhs), method)
+        },
+        _ => return Err(Diagnostic::error(codes::INVALID_CALLEE, "only named functions and methods can be called")
+            .with_label(start, "expected a function name")),
+    };
     parser.advance();
 
     let mut arguments = vec![];
 
     while parser.has_tokens() && !parser.is_current_kind(Token::CloseParen) {
-        arguments.push(parse_expression(parser, BindingPower::Default));
+        arguments.push(parse_expression(parser, BindingPower::Default)?);
 
         if !parser.is_current_kind(Token::CloseParen) {
-            parser.expect(Token::Comma);
+            parser.expect(Token::Comma)?;
         }
     }
-    parser.expect(Token::CloseParen);
+    parser.expect(Token::CloseParen)?;
+
+    let call = match receiver {
+        Some(receiver) => ExprKind::MethodCall { receiver, name, args: arguments, by_ref: false },
+        None => ExprKind::Call { name, args: arguments },
+    };
 
-    Expr::Call { name: callee, args: arguments }
+    Ok(Expr::new(call, parser.span_from(start)))
 }
 
-pub fn parse_op_equals_expression(parser: &mut Parser, left: Expr, bp: BindingPower) -> Expr {
-    let Expr::Symbol(lhs) = left.clone() else {
-        panic!("Expected symbol on LHS of 'OPERATOR=' expression, got {:?}", left);
+pub fn parse_op_equals_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
+    let (ExprKind::Symbol(_) | ExprKind::Access { .. } | ExprKind::Index { .. }) = left.kind else {
+        return Err(Diagnostic::error(codes::INVALID_ASSIGNMENT_TARGET, format!("invalid left hand side of {}", parser.current()))
+            .with_label(left.span, "expected a variable, field or element"));
     };
 
     let real_op = match parser.current() {
         Token::PlusEquals => Token::Plus,
         Token::MinusEquals => Token::Minus,
-        _ => panic!("Invalid 'OPERATOR=' expression {:?}", left),
+        Token::MultiplyEquals => Token::Multiply,
+        Token::DivideEquals => Token::Divide,
+        Token::ModEquals => Token::Mod,
+        Token::BitAndEquals => Token::Reference,
+        Token::BitOrEquals => Token::BitOr,
+        Token::BitXorEquals => Token::BitXor,
+        Token::ShiftLeftEquals => Token::ShiftLeft,
+        Token::ShiftRightEquals => Token::ShiftRight,
+        _ => return Err(parser.error(codes::UNEXPECTED_TOKEN, &format!("invalid compound assignment {}", parser.current()), "not a compound assignment")),
     };
 
     parser.advance();
 
-    Expr::Assignment { 
-        assignee: Box::new(left.clone()),
-        right: Box::new(Expr::Binary {
-            left: Box::new(left),
-            op: real_op,
-            right: Box::new(parse_expression(parser, BindingPower::Default)),
-        }),
-    }
+    let right = parse_expression(parser, BindingPower::Default)?;
+    let span = left.span.to(right.span);
+
+    Ok(Expr::new(ExprKind::CompoundAssignment { assignee: Box::new(left), op: real_op, right: Box::new(right) }, span))
 }
 
-pub fn parse_access_expression(parser: &mut Parser, left: Expr, bp: BindingPower) -> Expr {
+pub fn parse_access_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
     parser.advance();
 
-    let Token::Identifier(right) = parser.advance() else {
-        panic!("Expected identifier on rhs of access expression");
-    };
-    Expr::Access { lhs: Box::new(left), rhs: Box::new(Expr::Symbol(right.clone())) }
+    let right = parser.expect_identifier("expected field name after `.`")?;
+    let rhs = Expr::new(ExprKind::Symbol(right), parser.last_span());
+    let span = left.span.to(rhs.span);
+    Ok(Expr::new(ExprKind::Access { lhs: Box::new(left), rhs: Box::new(rhs) }, span))
 }
 
-pub fn parse_struct_create_expression(parser: &mut Parser) -> Expr {
+pub fn parse_index_expression(parser: &mut Parser, left: Expr, _bp: BindingPower) -> ParseResult<Expr> {
     parser.advance();
 
-    let Token::Identifier(name) = parser.advance() else {
-        panic!("Expected identifier for stuct initialization");
+    let struct_literals = std::mem::replace(&mut parser.struct_literals, true);
+    let kind = parse_index_contents(parser, left.clone());
+    parser.struct_literals = struct_literals;
+    let kind = kind?;
+
+    Ok(Expr::new(kind, left.span.to(parser.last_span())))
+}
+
+/// What's between the brackets of `a[i]` or `a[start..end]`, along with the closing bracket
+fn parse_index_contents(parser: &mut Parser, left: Expr) -> ParseResult<ExprKind> {
+    let start = if parser.is_current_kind(Token::Range) {
+        None
+    } else {
+        Some(Box::new(parse_expression(parser, BindingPower::Default)?))
     };
 
-    let newname = name.clone();
+    if !parser.is_current_kind(Token::Range) {
+        parser.expect(Token::CloseBracket)?;
+        return Ok(ExprKind::Index { target: Box::new(left), index: start.expect("Index was parsed") });
+    }
+    parser.advance();
+
+    let end = if parser.is_current_kind(Token::CloseBracket) {
+        None
+    } else {
+        Some(Box::new(parse_expression(parser, BindingPower::Default)?))
+    };
+    parser.expect(Token::CloseBracket)?;
+
+    Ok(ExprKind::Slice { target: Box::new(left), start, end })
+}
+
+pub fn parse_array_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
+    parser.advance();
+
+    let struct_literals = std::mem::replace(&mut parser.struct_literals, true);
+    let elements = parse_array_elements(parser);
+    parser.struct_literals = struct_literals;
+
+    Ok(Expr::new(ExprKind::Array(elements?), parser.span_from(start)))
+}
+
+fn parse_array_elements(parser: &mut Parser) -> ParseResult<Vec<Expr>> {
+    let mut elements = vec![];
+
+    while parser.has_tokens() && !parser.is_current_kind(Token::CloseBracket) {
+        elements.push(parse_expression(parser, BindingPower::Default)?);
+
+        if !parser.is_current_kind(Token::CloseBracket) {
+            parser.expect(Token::Comma)?;
+        }
+    }
+    parser.expect(Token::CloseBracket)?;
+
+    Ok(elements)
+}
+
+pub fn parse_new_expression(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
+    parser.advance();
+
+    if !matches!(parser.current(), Token::Identifier(_)) {
+        return Err(parser.error(codes::UNEXPECTED_TOKEN, &format!("expected struct name after `new` but found {}", parser.current()), "expected struct name"));
+    }
+
+    let value = parse_struct_literal(parser)?;
+    Ok(Expr::new(ExprKind::New(Box::new(value)), parser.span_from(start)))
+}
+
+pub fn parse_struct_literal(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
+    let name = parser.expect_identifier("expected struct name")?;
 
     let mut fields = vec![];
-    parser.expect(Token::OpenCurly);
+    parser.expect(Token::OpenCurly)?;
     while parser.has_tokens() && !parser.is_current_kind(Token::CloseCurly) {
-        let Token::Identifier(name) = parser.advance() else {
-            panic!("Expected identifier in struct initialization, but got {:?}", parser.current());
-        };
+        let field_start = parser.current_span();
+        let name = parser.expect_identifier("expected field name in struct initialization")?;
 
-        let nname = name.clone();
-
-        parser.expect(Token::Colon);
-        fields.push(Stmt::StructInitField { name: nname, value: Box::new(parse_expression(parser, BindingPower::Default)) });
+        parser.expect(Token::Colon)?;
+        let value = parse_expression(parser, BindingPower::Default)?;
+        fields.push(Stmt::new(StmtKind::StructInitField { name, value: Box::new(value) }, parser.span_from(field_start)));
 
         if !parser.is_current_kind(Token::CloseCurly) {
-            parser.expect(Token::Comma);
+            parser.expect(Token::Comma)?;
         }
     }
 
-    parser.expect(Token::CloseCurly);
+    parser.expect(Token::CloseCurly)?;
 
-    Expr::StructInitialize { name: newname, fields: fields }
-}
\ No newline at end of file
+    Ok(Expr::new(ExprKind::StructInitialize { name, fields }, parser.span_from(start)))
+}
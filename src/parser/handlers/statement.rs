@@ -1,262 +1,403 @@
-
-use crate::{lexer::Token, parser::{ast::{Expr, Stmt, Type}, pratt::BindingPower, Parser}};
+use crate::{diagnostics::codes, lexer::{Span, Token}, parser::{ast::{Expr, ExprKind, Stmt, StmtKind, Type, TypeKind}, pratt::BindingPower, ParseResult, Parser}};
 
 use super::{expression::*, types::*};
 
-pub fn parse_stmt(parser: &mut Parser) -> Stmt {
+pub fn parse_stmt(parser: &mut Parser) -> ParseResult<Stmt> {
     if let Some(handler) = parser.get_stmt(parser.current()) {
         return handler(parser)
     }
 
-    let expr = parse_expression(parser, BindingPower::Default);
-    
-    if let Expr::StmtHack(s) = expr {
-        return s;
+    if matches!(parser.current(), Token::Identifier(_)) && matches!(parser.peek(1), Token::Colon) {
+        return parse_labeled_stmt(parser);
     }
 
-    parser.expect(Token::Semicolon);
+    let expr = parse_expression(parser, BindingPower::Default)?;
 
-    Stmt::Expression(Box::new(expr))
-}
+    if let ExprKind::StmtHack(s) = expr.kind {
+        return Ok(s);
+    }
 
-pub fn parse_if(parser: &mut Parser) -> Stmt {
-    parser.advance();
+    parser.expect(Token::Semicolon)?;
+
+    let span = parser.span_from(expr.span);
+    Ok(Stmt::new(StmtKind::Expression(Box::new(expr)), span))
+}
 
-    let condition = parse_expression(parser, BindingPower::Default);
+pub fn parse_block(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
     let mut body = vec![];
-    let mut _else = None;
 
-    parser.expect(Token::OpenCurly);
+    parser.expect(Token::OpenCurly)?;
     while parser.has_tokens() && !parser.is_current_kind(Token::CloseCurly) {
-        body.push(parse_stmt(parser));
+        let position = parser.position();
+        match parse_stmt(parser) {
+            Ok(stmt) => body.push(stmt),
+            Err(e) => parser.recover(e, position),
+        }
     }
 
-    parser.expect(Token::CloseCurly);
+    parser.expect(Token::CloseCurly)?;
+
+    Ok(Stmt::new(StmtKind::Block(body), parser.span_from(start)))
+}
+
+pub fn parse_if(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    parser.advance();
+
+    let condition = parse_block_condition(parser)?;
+    let body = parse_block(parser)?;
+    let mut _else = None;
 
     if parser.is_current_kind(Token::Else) {
         parser.advance();
         if parser.is_current_kind(Token::If) {
-            _else = Some(Box::new(parse_if(parser)));
+            _else = Some(Box::new(parse_if(parser)?));
         } else {
-            let mut _else_body = vec![];
-            parser.expect(Token::OpenCurly);
-            while parser.has_tokens() && !parser.is_current_kind(Token::CloseCurly) {
-                _else_body.push(parse_stmt(parser));
-            }
-        
-            parser.expect(Token::CloseCurly);
-
-            _else = Some(Box::new(Stmt::Block(_else_body)));
+            _else = Some(Box::new(parse_block(parser)?));
         }
     }
 
-    Stmt::If { condition: Box::new(condition), body: Box::new(Stmt::Block(body)), _else }
+    Ok(Stmt::new(StmtKind::If { condition: Box::new(condition), body: Box::new(body), _else }, parser.span_from(start)))
 }
 
 
-pub fn parse_while(parser: &mut Parser) -> Stmt {
+pub fn parse_while(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    parse_while_loop(parser, None, start)
+}
+
+fn parse_while_loop(parser: &mut Parser, label: Option<String>, start: Span) -> ParseResult<Stmt> {
     parser.advance();
 
-    let condition = parse_expression(parser, BindingPower::Default);
-    let mut body = vec![];
+    let condition = parse_block_condition(parser)?;
+    let body = parse_block(parser)?;
 
-    parser.expect(Token::OpenCurly);
-    while parser.has_tokens() && !parser.is_current_kind(Token::CloseCurly) {
-        body.push(parse_stmt(parser));
+    Ok(Stmt::new(StmtKind::While { condition: Box::new(condition), body: Box::new(body), label }, parser.span_from(start)))
+}
+
+/// Parses the expression that comes right before a block, where `name {` is the start of the block
+fn parse_block_condition(parser: &mut Parser) -> ParseResult<Expr> {
+    let struct_literals = std::mem::replace(&mut parser.struct_literals, false);
+    let expr = parse_expression(parser, BindingPower::Default);
+    parser.struct_literals = struct_literals;
+    expr
+}
+
+pub fn parse_for(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    parse_for_loop(parser, None, start)
+}
+
+fn parse_for_loop(parser: &mut Parser, label: Option<String>, start: Span) -> ParseResult<Stmt> {
+    parser.advance();
+
+    let variable = parser.expect_identifier("expected loop variable name after `for`")?;
+    parser.expect(Token::In)?;
+
+    let iterable = if is_range_ahead(parser) {
+        parse_range(parser)?
+    } else {
+        parse_block_condition(parser)?
+    };
+
+    let body = parse_block(parser)?;
+
+    Ok(Stmt::new(StmtKind::For { variable, iterable: Box::new(iterable), body: Box::new(body), label }, parser.span_from(start)))
+}
+
+/// Whether the current token opens `[start..end]` rather than an array, which is
+/// when a `..` or `..=` comes before the closing bracket outside of any nesting
+fn is_range_ahead(parser: &Parser) -> bool {
+    if !parser.is_current_kind(Token::OpenBracket) {
+        return false;
     }
 
-    parser.expect(Token::CloseCurly);
+    let mut depth = 0;
+    let mut offset = 1;
+    loop {
+        match parser.peek(offset) {
+            Token::OpenBracket | Token::OpenParen | Token::OpenCurly => depth += 1,
+            Token::CloseBracket | Token::CloseParen | Token::CloseCurly if depth == 0 => return false,
+            Token::CloseBracket | Token::CloseParen | Token::CloseCurly => depth -= 1,
+            Token::Range | Token::RangeInclusive if depth == 0 => return true,
+            Token::Eof => return false,
+            _ => {},
+        }
+        offset += 1;
+    }
+}
+
+fn parse_range(parser: &mut Parser) -> ParseResult<Expr> {
+    let start = parser.current_span();
+    parser.advance();
 
-    Stmt::While { condition: Box::new(condition), body: Box::new(Stmt::Block(body)) }
+    let from = parse_expression(parser, BindingPower::Default)?;
+    let inclusive = match parser.current() {
+        Token::Range => false,
+        Token::RangeInclusive => true,
+        _ => return Err(parser.error(
+            codes::UNEXPECTED_TOKEN,
+            &format!("expected `..` or `..=` in range but found {}", parser.current()),
+            "expected `..` or `..=` here",
+        )),
+    };
+    parser.advance();
+
+    let to = parse_expression(parser, BindingPower::Default)?;
+
+    let mut step = None;
+    if parser.is_current_kind(Token::Colon) {
+        parser.advance();
+        step = Some(Box::new(parse_expression(parser, BindingPower::Default)?));
+    }
+
+    parser.expect(Token::CloseBracket)?;
+
+    Ok(Expr::new(ExprKind::Range { start: Box::new(from), end: Box::new(to), inclusive, step }, parser.span_from(start)))
+}
+
+/// `name: while ...`, lets `break name;` and `continue name;` reach an outer loop
+pub fn parse_labeled_stmt(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    let label = parser.expect_identifier("expected loop label")?;
+    parser.expect(Token::Colon)?;
+
+    match parser.current() {
+        Token::While => parse_while_loop(parser, Some(label), start),
+        Token::For => parse_for_loop(parser, Some(label), start),
+        _ => Err(parser.error(
+            codes::UNEXPECTED_TOKEN,
+            &format!("expected a loop after label `{}` but found {}", label, parser.current()),
+            "expected `while` or `for`",
+        )),
+    }
+}
+
+fn parse_loop_label(parser: &mut Parser) -> ParseResult<Option<String>> {
+    let label = if let Token::Identifier(label) = parser.current() {
+        let label = label.clone();
+        parser.advance();
+        Some(label)
+    } else {
+        None
+    };
+
+    parser.expect(Token::Semicolon)?;
+    Ok(label)
 }
 
-pub fn parse_function_declaration(parser: &mut Parser, name: String) -> Stmt {
-    parser.expect(Token::OpenParen);
+pub fn parse_break(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    parser.advance();
+
+    let label = parse_loop_label(parser)?;
+    Ok(Stmt::new(StmtKind::Break { label }, parser.span_from(start)))
+}
+
+pub fn parse_continue(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    parser.advance();
+
+    let label = parse_loop_label(parser)?;
+    Ok(Stmt::new(StmtKind::Continue { label }, parser.span_from(start)))
+}
+
+pub fn parse_function_declaration(parser: &mut Parser, name: String, start: Span) -> ParseResult<Stmt> {
+    parser.expect(Token::OpenParen)?;
 
     let mut arguments = vec![];
 
     while parser.has_tokens() && !parser.is_current_kind(Token::CloseParen) {
+        let arg_start = parser.current_span();
         match parser.advance() {
-            Token::Reference => { 
-                let Token::Identifier(ident) = parser.expect(Token::Identifier(String::new())) else {
-                    panic!("Only put a '&' symbol behind 'this' to take it as a reference, otherwise use it before type");
-                };
-                if ident != "this" {
-                    panic!("Only put a '&' symbol behind 'this' to take it as a reference, otherwise use it before type");
+            Token::Reference => {
+                let is_this = matches!(parser.current(), Token::Identifier(ident) if ident == "this");
+                if !is_this {
+                    return Err(parser.error(
+                        codes::INVALID_RECEIVER,
+                        "only `this` can be taken by reference with a leading `&`",
+                        "expected `this`",
+                    ).with_note("to take a reference argument, put `&` before the type: `name &Type`"));
                 }
-                arguments.push(Stmt::Field { name: "this".into(), _type: Box::new(Type::Ref(Box::new(Type::Symbol("this".into())))) })
+                parser.advance();
+
+                let span = parser.span_from(arg_start);
+                let _type = Type::new(TypeKind::Ref(Box::new(Type::new(TypeKind::Symbol("this".into()), parser.last_span()))), span);
+                arguments.push(Stmt::new(StmtKind::Field { name: "this".into(), _type: Box::new(_type) }, span))
             },
             Token::Identifier(name) => {
                 if name == "this" {
-                    arguments.push(Stmt::Field { name: "this".into(), _type: Box::new(Type::Symbol("this".into()))});
+                    let span = parser.last_span();
+                    arguments.push(Stmt::new(StmtKind::Field { name: "this".into(), _type: Box::new(Type::new(TypeKind::Symbol("this".into()), span)) }, span));
                 } else {
-                    arguments.push(Stmt::Field { name: name.into(), _type: Box::new(parse_type(parser, BindingPower::Default)) });
+                    let name = name.clone();
+                    let _type = parse_type(parser, BindingPower::Default)?;
+                    arguments.push(Stmt::new(StmtKind::Field { name, _type: Box::new(_type) }, parser.span_from(arg_start)));
                 }
             },
-            _ => panic!("Expected identifier or 'this' in fields for function {}", name),
+            _ => return Err(parser.error_last(
+                codes::UNEXPECTED_TOKEN,
+                &format!("expected argument name or `this` in arguments of function `{}` but found {}", name, parser.last()),
+                "expected argument",
+            )),
         }
 
         if !parser.is_current_kind(Token::CloseParen) {
-            parser.expect(Token::Comma);
+            parser.expect(Token::Comma)?;
         }
     }
 
-    parser.expect(Token::CloseParen);
+    parser.expect(Token::CloseParen)?;
 
-    let return_type = if parser.is_current_kind(Token::Identifier(String::new())) {
-        parse_type(parser, BindingPower::Default)
+    let return_type = if parser.get_type_nud(parser.current()).is_some() {
+        parse_type(parser, BindingPower::Default)?
     } else {
-        Type::Symbol("void".into())
+        Type::new(TypeKind::Symbol("void".into()), parser.last_span())
     };
 
-    let mut body = vec![];
-
-    parser.expect(Token::OpenCurly);
-    while parser.has_tokens() && !parser.is_current_kind(Token::CloseCurly) {
-        body.push(parse_stmt(parser));
-    }
-
-    parser.expect(Token::CloseCurly);
+    let body = parse_block(parser)?;
 
-    Stmt::FunctionDeclaration {
+    Ok(Stmt::new(StmtKind::FunctionDeclaration {
         name,
         return_type: Box::new(return_type),
         arguments,
-        body: Box::new(Stmt::Block(body))
-    }
+        body: Box::new(body)
+    }, parser.span_from(start)))
 }
 
-pub fn parse_struct_declaration(parser: &mut Parser, name: String) -> Stmt {
-    parser.expect(Token::OpenCurly);
+pub fn parse_struct_declaration(parser: &mut Parser, name: String, start: Span) -> ParseResult<Stmt> {
+    parser.expect(Token::OpenCurly)?;
 
     let mut fields = vec![];
     let mut functions = vec![];
 
     while parser.has_tokens() && !parser.is_current_kind(Token::CloseCurly) {
-        let Token::Identifier(field_name) = parser.advance() else {
-            panic!("Expected identifier for struct field name");
-        };
-        let name = field_name.clone();
+        let field_start = parser.current_span();
+        let name = parser.expect_identifier("expected struct field name")?;
 
         if parser.is_current_kind(Token::Arrow) {
             parser.advance();
-            parser.expect(Token::Subroutine);
+            parser.expect(Token::Subroutine)?;
 
-            functions.push(parse_function_declaration(parser, name));
+            functions.push(parse_function_declaration(parser, name, field_start)?);
             continue;
         }
-        
-        let _type = parse_type(parser, BindingPower::Default);
 
-        fields.push(Stmt::Field { name, _type: Box::new(_type) });
+        let _type = parse_type(parser, BindingPower::Default)?;
+
+        fields.push(Stmt::new(StmtKind::Field { name, _type: Box::new(_type) }, parser.span_from(field_start)));
         if !parser.is_current_kind(Token::CloseCurly) {
-            parser.expect(Token::Comma);
+            parser.expect(Token::Comma)?;
         }
     }
 
-    parser.advance();
+    parser.expect(Token::CloseCurly)?;
 
-    Stmt::StructDeclaration { name, fields, functions }
+    Ok(Stmt::new(StmtKind::StructDeclaration { name, fields, functions }, parser.span_from(start)))
 }
 
-pub fn parse_var_decl(parser: &mut Parser) -> Stmt {
-    parser.advance();
-
-    let Token::Identifier(var_name) = parser.advance() else {
-        panic!("Expected identifier for variable name, got {:?}", parser.last())
-    };
+pub fn parse_var_decl(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    let mutable = matches!(parser.advance(), Token::Mut);
 
-    let name = var_name.clone();
+    let name = parser.expect_identifier("expected variable name")?;
 
     let mut explicit_type = None;
     if parser.is_current_kind(Token::Colon) {
         parser.advance();
-        explicit_type = Some(Box::new(parse_type(parser, BindingPower::Default)));
+        explicit_type = Some(Box::new(parse_type(parser, BindingPower::Default)?));
     }
 
-    parser.expect(Token::Assignment);
-    let val = parse_expression(parser, BindingPower::Default);
+    parser.expect(Token::Assignment)?;
+    let val = parse_expression(parser, BindingPower::Default)?;
 
-    parser.expect(Token::Semicolon);
+    parser.expect(Token::Semicolon)?;
 
-    Stmt::VariableDeclaration { name, explicit_type, value: Box::new(val) }
+    Ok(Stmt::new(StmtKind::VariableDeclaration { name, mutable, explicit_type, value: Box::new(val) }, parser.span_from(start)))
 }
 
-pub fn parse_extern(parser: &mut Parser, name: String) -> Stmt {
+pub fn parse_extern(parser: &mut Parser, name: String, start: Span) -> ParseResult<Stmt> {
     let mut symbol = name.clone();
-    
-    if parser.is_current_kind(Token::OpenBracket) {
-        parser.advance();
-        let Token::Identifier(s) = parser.advance() else {
-            panic!("Expected identifier, example: [SYMBOL]");
-        };
-        symbol = s.into();
-    }
 
-    if parser.is_current_kind(Token::CloseBracket) {
+    if parser.is_current_kind(Token::OpenBracket) {
         parser.advance();
+        symbol = parser.expect_identifier("expected symbol name, example: [SYMBOL]")?;
+        parser.expect(Token::CloseBracket)?;
     }
 
-    parser.expect(Token::OpenParen);
+    parser.expect(Token::OpenParen)?;
 
     let mut arguments = vec![];
 
     while parser.has_tokens() && !parser.is_current_kind(Token::CloseParen) {
-        match parser.advance() {
-            Token::Identifier(name) => arguments.push(Stmt::Field { name: name.into(), _type: Box::new(parse_type(parser, BindingPower::Default)) }),
-            _ => panic!("Expected identifier in fields for extern function {}", name),
-        }
+        let arg_start = parser.current_span();
+        let name = parser.expect_identifier(&format!("expected argument name in arguments of extern function `{}`", name))?;
+        let _type = parse_type(parser, BindingPower::Default)?;
+        arguments.push(Stmt::new(StmtKind::Field { name, _type: Box::new(_type) }, parser.span_from(arg_start)));
 
         if !parser.is_current_kind(Token::CloseParen) {
-            parser.expect(Token::Comma);
+            parser.expect(Token::Comma)?;
         }
     }
 
-    parser.expect(Token::CloseParen);
+    parser.expect(Token::CloseParen)?;
 
-    let return_type = if parser.is_current_kind(Token::Identifier(String::new())) {
-        parse_type(parser, BindingPower::Default)
+    let return_type = if parser.get_type_nud(parser.current()).is_some() {
+        parse_type(parser, BindingPower::Default)?
     } else {
-        Type::Symbol("void".into())
+        Type::new(TypeKind::Symbol("void".into()), parser.last_span())
     };
 
-    parser.expect(Token::Semicolon);
+    parser.expect(Token::Semicolon)?;
 
-    Stmt::Extern { name, symbol, return_type: Box::new(return_type), arguments }
+    Ok(Stmt::new(StmtKind::Extern { name, symbol, return_type: Box::new(return_type), arguments }, parser.span_from(start)))
 }
 
-pub fn parse_link_lib(parser: &mut Parser) -> Stmt {
+pub fn parse_link_lib(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
     parser.advance();
-    let Token::String(libname) = parser.advance() else {
-        panic!("Expected string for linklib argbument")
-    };
+    let library = parser.expect_string("expected string for linklib argument")?;
 
-    Stmt::Link { library: libname.into(), _static: false }
+    Ok(Stmt::new(StmtKind::Link { library, _static: false }, parser.span_from(start)))
 }
 
-pub fn parse_link_static(parser: &mut Parser) -> Stmt {
+pub fn parse_link_static(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
     parser.advance();
-    let Token::String(libname) = parser.advance() else {
-        panic!("Expected string for linkstatic argbument")
+    let library = parser.expect_string("expected string for linkstatic argument")?;
+
+    Ok(Stmt::new(StmtKind::Link { library, _static: true }, parser.span_from(start)))
+}
+
+pub fn parse_return(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
+    parser.advance();
+
+    let value = if parser.is_current_kind(Token::Semicolon) {
+        None
+    } else {
+        Some(Box::new(parse_expression(parser, BindingPower::Default)?))
     };
+    parser.expect(Token::Semicolon)?;
 
-    Stmt::Link { library: libname.into(), _static: true }
+    Ok(Stmt::new(StmtKind::Return { value }, parser.span_from(start)))
 }
 
-pub fn parse_return(parser: &mut Parser) -> Stmt {
+pub fn parse_delete(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
     parser.advance();
 
-    let val = parse_expression(parser, BindingPower::Default);
-    parser.expect(Token::Semicolon);
+    let value = parse_expression(parser, BindingPower::Default)?;
+    parser.expect(Token::Semicolon)?;
 
-    Stmt::Return { value: Box::new(val) }
+    Ok(Stmt::new(StmtKind::Delete { value: Box::new(value) }, parser.span_from(start)))
 }
 
-pub fn parse_include(parser: &mut Parser) -> Stmt {
+pub fn parse_include(parser: &mut Parser) -> ParseResult<Stmt> {
+    let start = parser.current_span();
     parser.advance();
-    let Token::String(val) = parser.advance() else {
-        panic!("Expected string for include path");
-    };
+    let path = parser.expect_string("expected string for include path")?;
 
-    Stmt::Include { path: val.into() }
-}
\ No newline at end of file
+    Ok(Stmt::new(StmtKind::Include { path, module: None }, parser.span_from(start)))
+}
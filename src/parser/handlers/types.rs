@@ -1,40 +1,60 @@
-use crate::{lexer::Token, parser::{ast::Type, pratt::BindingPower, Parser}};
-
-pub fn parse_type(parser: &mut Parser, bp: BindingPower) -> Type {
-    let nud_fn = parser.get_type_nud(parser.current()).unwrap_or_else(|| panic!("NUD TYPE FUNCTION DOESNT EXIST FOR TOKEN {:?}", parser.current()));
-    let mut left = nud_fn(parser);
-    loop {
-        if let Some(bp_) = parser.get_bp(parser.current()) {
-            if *bp_ as usize <= bp as usize {
-                break;
-            }
-            let led_fn = parser.get_type_led(parser.current()).unwrap_or_else(|| panic!("LED TYPE FUNCTION DOESNT EXIST FOR TOKEN {:?}", parser.current()));
-            left = led_fn(parser, left, bp);
-            
-        } else {
+use crate::{diagnostics::codes, lexer::{Number, Token}, parser::{ast::{Type, TypeKind}, pratt::BindingPower, ParseResult, Parser}};
+
+pub fn parse_type(parser: &mut Parser, bp: BindingPower) -> ParseResult<Type> {
+    let Some(nud_fn) = parser.get_type_nud(parser.current()) else {
+        return Err(parser.error(codes::EXPECTED_TYPE, &format!("expected type but found {}", parser.current()), "expected type"));
+    };
+    let mut left = nud_fn(parser)?;
+    while let Some(bp_) = parser.get_bp(parser.current()) {
+        if *bp_ as usize <= bp as usize {
             break;
         }
+        let Some(led_fn) = parser.get_type_led(parser.current()) else {
+            break;
+        };
+        left = led_fn(parser, left, bp)?;
     }
-    left
+    Ok(left)
 }
 
-pub fn parse_symbol_type(parser: &mut Parser) -> Type {
-    let Token::Identifier(ident) = parser.expect(Token::Identifier(String::new())) else {
-        panic!("Expected identifier for symbol type, got {:?}", parser.current())
-    };
+pub fn parse_symbol_type(parser: &mut Parser) -> ParseResult<Type> {
+    let ident = parser.expect_identifier("expected type name")?;
 
-    Type::Symbol(ident.into())
+    Ok(Type::new(TypeKind::Symbol(ident), parser.last_span()))
 }
 
-pub fn parse_array_type(parser: &mut Parser) -> Type {
+pub fn parse_array_type(parser: &mut Parser) -> ParseResult<Type> {
+    let start = parser.current_span();
     parser.advance();
-    parser.expect(Token::CloseBracket);
 
-    Type::Array(Box::new(parse_type(parser, BindingPower::Default)))
+    let len = match *parser.current() {
+        Token::Integer(Number { value, suffix: None | Some("usize") }) => {
+            parser.advance();
+            Some(value as usize)
+        },
+        Token::CloseBracket => None,
+        _ => return Err(parser.error(codes::UNEXPECTED_TOKEN, &format!("expected array length but found {}", parser.current()), "expected a length such as `[4]`")),
+    };
+    parser.expect(Token::CloseBracket)?;
+
+    let mutable = len.is_none() && parser.is_current_kind(Token::Mut);
+    if mutable {
+        parser.advance();
+    }
+
+    let inner = Box::new(parse_type(parser, BindingPower::Default)?);
+    let kind = match len {
+        Some(len) => TypeKind::Array { len, inner },
+        None => TypeKind::Slice { inner, mutable },
+    };
+    Ok(Type::new(kind, parser.span_from(start)))
 }
 
 
-pub fn parse_ref_type(parser: &mut Parser) -> Type {
+pub fn parse_ref_type(parser: &mut Parser) -> ParseResult<Type> {
+    let start = parser.current_span();
     parser.advance();
-    Type::Ref(Box::new(parse_type(parser, BindingPower::Default)))
+
+    let inner = parse_type(parser, BindingPower::Default)?;
+    Ok(Type::new(TypeKind::Ref(Box::new(inner)), parser.span_from(start)))
 }
@@ -1,32 +1,40 @@
 use std::mem;
 
-use ast::{Expr, Stmt, Type};
+use ast::{Expr, Stmt, StmtKind, Type};
 use handlers::{expression::*, statement::*, types::*};
 use pratt::{BindingPower, LedHandler, NudHandler, PrattLookups, StmtHandler};
 
-use crate::lexer::Token;
+use crate::{diagnostics::{codes, Diagnostic, Diagnostics}, lexer::{Number, Span, SpannedToken, Token}};
 
 pub mod ast;
 pub mod pratt;
 pub mod lookup;
 pub mod handlers;
 
+pub type ParseResult<T> = Result<T, Diagnostic>;
+
 pub struct Parser {
     lookup: PrattLookups<Expr, Stmt>,
     type_lookup: PrattLookups<Type, Stmt>,
 
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     position: usize,
+
+    errors: Vec<Diagnostic>,
+
+    /// Off while parsing the expression before a block, where `name {` opens the block
+    struct_literals: bool,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<SpannedToken>) -> Self {
         let mut lu = PrattLookups::new();
         let mut tlu = PrattLookups::new();
 
-        lu.led(Token::And, BindingPower::Logical, parse_binary_expression);
-        lu.led(Token::Or, BindingPower::Logical, parse_binary_expression);
-        lu.led(Token::Range, BindingPower::Logical, parse_binary_expression);
+        lu.led(Token::Question, BindingPower::Ternary, parse_ternary_expression);
+
+        lu.led(Token::Or, BindingPower::LogicalOr, parse_binary_expression);
+        lu.led(Token::And, BindingPower::LogicalAnd, parse_binary_expression);
 
         lu.led(Token::Less, BindingPower::Relational, parse_binary_expression);
         lu.led(Token::LessOrEqual, BindingPower::Relational, parse_binary_expression);
@@ -35,6 +43,13 @@ impl Parser {
         lu.led(Token::Equals, BindingPower::Relational, parse_binary_expression);
         lu.led(Token::NotEquals, BindingPower::Relational, parse_binary_expression);
 
+        lu.led(Token::BitOr, BindingPower::BitwiseOr, parse_binary_expression);
+        lu.led(Token::BitXor, BindingPower::BitwiseXor, parse_binary_expression);
+        lu.led(Token::Reference, BindingPower::BitwiseAnd, parse_binary_expression);
+
+        lu.led(Token::ShiftLeft, BindingPower::Shift, parse_binary_expression);
+        lu.led(Token::ShiftRight, BindingPower::Shift, parse_binary_expression);
+
         lu.led(Token::Plus, BindingPower::Additive, parse_binary_expression);
         lu.led(Token::Minus, BindingPower::Additive, parse_binary_expression);
 
@@ -42,36 +57,60 @@ impl Parser {
 
         lu.led(Token::Multiply, BindingPower::Multiplicative, parse_binary_expression);
         lu.led(Token::Divide, BindingPower::Multiplicative, parse_binary_expression);
-        lu.led(Token::Power, BindingPower::Multiplicative, parse_binary_expression);
         lu.led(Token::Mod, BindingPower::Multiplicative, parse_binary_expression);
-        
-        lu.led(Token::Arrow, BindingPower::Primary, parse_arrow_expression);
+        lu.led(Token::Power("^^"), BindingPower::Exponent, parse_binary_expression);
 
-        lu.led(Token::Dot, BindingPower::Member, parse_access_expression);
-        lu.led(Token::OpenParen, BindingPower::Call, parse_call_expression);
 
-        lu.led(Token::PlusEquals, BindingPower::Call, parse_op_equals_expression);
-        lu.led(Token::MinusEquals, BindingPower::Call, parse_op_equals_expression);
+        lu.led(Token::As, BindingPower::Unary, parse_cast_expression);
 
-        lu.nud(Token::New, parse_struct_create_expression);
+        lu.led(Token::Arrow, BindingPower::Primary, parse_arrow_expression);
 
-        lu.nud(Token::Integer(0), parse_primary_expression);
-        lu.nud(Token::Decimel(0.), parse_primary_expression);
+        lu.led(Token::Dot, BindingPower::Member, parse_access_expression);
+        lu.led(Token::OpenParen, BindingPower::Call, parse_call_expression);
+        lu.led(Token::OpenBracket, BindingPower::Call, parse_index_expression);
+
+        lu.led(Token::PlusPlus, BindingPower::Call, parse_postfix_increment_expression);
+        lu.led(Token::MinusMinus, BindingPower::Call, parse_postfix_increment_expression);
+
+        lu.led(Token::PlusEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::MinusEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::MultiplyEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::DivideEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::ModEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::BitAndEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::BitOrEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::BitXorEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::ShiftLeftEquals, BindingPower::Assignment, parse_op_equals_expression);
+        lu.led(Token::ShiftRightEquals, BindingPower::Assignment, parse_op_equals_expression);
+
+        lu.nud(Token::New, parse_new_expression);
+
+        lu.nud(Token::Integer(Number { value: 0, suffix: None }), parse_primary_expression);
+        lu.nud(Token::Decimel(Number { value: 0., suffix: None }), parse_primary_expression);
         lu.nud(Token::String(String::new()), parse_primary_expression);
+        lu.nud(Token::Char('\0'), parse_primary_expression);
         lu.nud(Token::Identifier(String::new()), parse_primary_expression);
         lu.nud(Token::True, parse_bool_expression);
         lu.nud(Token::False, parse_bool_expression);
 
-        // lu.nud(Token::OpenBracket, parse_struct_create_expression);
+        lu.nud(Token::OpenBracket, parse_array_expression);
         lu.nud(Token::Not, parse_prefix_expression);
+        lu.nud(Token::BitNot, parse_prefix_expression);
         lu.nud(Token::OpenParen, parse_grouped_expression);
 
         lu.nud(Token::Minus, parse_prefix_expression);
+        lu.nud(Token::PlusPlus, parse_prefix_increment_expression);
+        lu.nud(Token::MinusMinus, parse_prefix_increment_expression);
 
         lu.stmt(Token::If, parse_if);
         lu.stmt(Token::While, parse_while);
+        lu.stmt(Token::For, parse_for);
+        lu.stmt(Token::Break, parse_break);
+        lu.stmt(Token::Continue, parse_continue);
         lu.stmt(Token::Let, parse_var_decl);
+        lu.stmt(Token::Mut, parse_var_decl);
         lu.stmt(Token::Return, parse_return);
+        lu.stmt(Token::Delete, parse_delete);
         lu.stmt(Token::LinkStatic, parse_link_static);
         lu.stmt(Token::LinkLib, parse_link_lib);
         lu.stmt(Token::Include, parse_include);
@@ -86,36 +125,123 @@ impl Parser {
 
             tokens,
             position: 0,
+
+            errors: vec![],
+
+            struct_literals: true,
         }
     }
 
-    pub fn parse(tokens: Vec<Token>) -> Stmt {
+    pub fn parse(tokens: Vec<SpannedToken>, diagnostics: &mut Diagnostics) -> Stmt {
         let mut body = vec![];
 
         let mut parser = Parser::new(tokens);
+        let start = parser.current_span();
         while parser.has_tokens() {
-            body.push(parse_stmt(&mut parser));
+            let position = parser.position;
+            match parse_stmt(&mut parser) {
+                Ok(stmt) => body.push(stmt),
+                Err(e) => {
+                    parser.recover(e, position);
+
+                    // Nothing is left open at the top level for a stray `}` to close
+                    if parser.has_tokens() && parser.is_current_kind(Token::CloseCurly) {
+                        parser.advance();
+                    }
+                },
+            }
+        }
+
+        for error in parser.errors.drain(..) {
+            diagnostics.report(error);
         }
                 
-        Stmt::Block(body)
+        Stmt::new(StmtKind::Block(body), start.to(parser.current_span()))
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Records `error` and skips ahead to a point where parsing can resume,
+    /// always making progress past the statement that started at `start`
+    pub fn recover(&mut self, error: Diagnostic, start: usize) {
+        self.errors.push(error);
+        self.synchronize();
+
+        if self.position == start && self.has_tokens() {
+            self.advance();
+        }
+    }
+
+    /// Skips tokens until just after a `;`, just after a skipped `{ ... }` group,
+    /// at an unmatched `}` or at the start of a `name -> ...` declaration
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+
+        while self.has_tokens() {
+            match self.current() {
+                Token::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                },
+                Token::OpenCurly => depth += 1,
+                Token::CloseCurly => {
+                    if depth == 0 {
+                        return;
+                    }
+
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                },
+                Token::Identifier(_) if depth == 0 && self.is_declaration_start() => return,
+                _ => {},
+            }
+            self.advance();
+        }
+    }
+
+    fn is_declaration_start(&self) -> bool {
+        matches!(self.peek(1), Token::Arrow)
     }
 
     pub fn current(&self) -> &Token {
-        &self.tokens[self.position]
+        &self.tokens[self.position].token
+    }
+
+    /// Looks `offset` tokens past the current one without consuming anything
+    pub fn peek(&self, offset: usize) -> &Token {
+        &self.tokens[(self.position + offset).min(self.tokens.len() - 1)].token
     }
 
     pub fn last(&self) -> &Token {
-        &self.tokens[self.position-1]
+        &self.tokens[self.position-1].token
+    }
+
+    pub fn current_span(&self) -> Span {
+        self.tokens[self.position].span
+    }
+
+    pub fn last_span(&self) -> Span {
+        self.tokens[self.position-1].span
+    }
+
+    /// Span from `start` up to the end of the last consumed token
+    pub fn span_from(&self, start: Span) -> Span {
+        start.to(self.last_span())
     }
 
     pub fn advance(&mut self) -> &Token {
-        let tok = &self.tokens[self.position];
+        let tok = &self.tokens[self.position].token;
         self.position += 1;
         tok
     }
 
     pub fn has_tokens(&mut self) -> bool {
-        self.position < self.tokens.len() && !self.is_current_kind(Token::EOF)
+        self.position < self.tokens.len() && !self.is_current_kind(Token::Eof)
     }
 
     pub fn get_stmt(&self, token: &Token) -> Option<&StmtHandler<Stmt>> {
@@ -142,15 +268,44 @@ impl Parser {
         self.lookup.get_bp(token)
     }
 
-    pub fn expect(&mut self, token: Token) -> &Token {
-        self.expect_error(token.clone(), &format!("Expected token {:?} but got {:?}", token, self.current()))
+    pub fn expect(&mut self, token: Token) -> ParseResult<&Token> {
+        let error = format!("expected {} but found {}", describe_kind(&token), self.current());
+        self.expect_error(token, &error)
     }
 
-    pub fn expect_error(&mut self, token: Token, error: &str) -> &Token {
-        if self.is_current_kind(token) {
-            return self.advance();
+    pub fn expect_error(&mut self, token: Token, error: &str) -> ParseResult<&Token> {
+        if self.is_current_kind(token.clone()) {
+            return Ok(self.advance());
         }
-        panic!("{}", error);
+        Err(self.error(codes::UNEXPECTED_TOKEN, error, &format!("expected {} here", describe_kind(&token))))
+    }
+
+    pub fn expect_identifier(&mut self, error: &str) -> ParseResult<String> {
+        if let Token::Identifier(name) = self.current() {
+            let name = name.clone();
+            self.advance();
+            return Ok(name);
+        }
+        Err(self.error(codes::UNEXPECTED_TOKEN, &format!("{} but found {}", error, self.current()), "expected identifier"))
+    }
+
+    pub fn expect_string(&mut self, error: &str) -> ParseResult<String> {
+        if let Token::String(value) = self.current() {
+            let value = value.clone();
+            self.advance();
+            return Ok(value);
+        }
+        Err(self.error(codes::UNEXPECTED_TOKEN, &format!("{} but found {}", error, self.current()), "expected string"))
+    }
+
+    /// Builds an error pointing at the current token
+    pub fn error(&self, code: &'static str, message: &str, label: &str) -> Diagnostic {
+        Diagnostic::error(code, message).with_label(self.current_span(), label)
+    }
+
+    /// Builds an error pointing at the last consumed token
+    pub fn error_last(&self, code: &'static str, message: &str, label: &str) -> Diagnostic {
+        Diagnostic::error(code, message).with_label(self.last_span(), label)
     }
 
     pub fn is_current_kind(&self, token: Token) -> bool {
@@ -159,6 +314,261 @@ impl Parser {
     }
 }
 
+/// Describes a token kind without the placeholder payload used for matching
+fn describe_kind(token: &Token) -> String {
+    match token {
+        Token::Identifier(_) => "identifier".into(),
+        Token::String(_) => "string".into(),
+        Token::Char(_) => "character".into(),
+        Token::Integer(_) => "integer".into(),
+        Token::Decimel(_) => "decimal".into(),
+        _ => token.to_string(),
+    }
+}
+
 pub fn is_kind<T>(lhs: T, rhs: T) -> bool {
     mem::discriminant(&lhs) == mem::discriminant(&rhs)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer, parser::ast::{ExprKind, TypeKind}};
+
+    fn text(source: &str, span: Span) -> &str {
+        &source[span.start..span.end]
+    }
+
+    #[test]
+    fn nodes_cover_their_source() {
+        let source = "main -> sub(a i32) i32 {\n    let x = a + b * 2;\n    return x;\n}";
+        let mut diagnostics = Diagnostics::new();
+        let ast = Parser::parse(lexer::tokenize(source, 0, &mut diagnostics), &mut diagnostics);
+        assert!(!diagnostics.has_errors());
+        let StmtKind::Block(body) = &ast.kind else {
+            panic!("Expected the program to be a block");
+        };
+        let StmtKind::FunctionDeclaration { return_type, arguments, body: function, .. } = &body[0].kind else {
+            panic!("Expected a function declaration");
+        };
+        assert_eq!(text(source, body[0].span), source);
+        assert_eq!(text(source, return_type.span), "i32");
+        assert_eq!(text(source, arguments[0].span), "a i32");
+
+        let StmtKind::Block(statements) = &function.kind else {
+            panic!("Expected the function body to be a block");
+        };
+        let StmtKind::VariableDeclaration { value, .. } = &statements[0].kind else {
+            panic!("Expected a variable declaration");
+        };
+        assert_eq!(text(source, statements[0].span), "let x = a + b * 2;");
+        assert_eq!(text(source, value.span), "a + b * 2");
+
+        let ExprKind::Binary { right, .. } = &value.kind else {
+            panic!("Expected a binary expression");
+        };
+        assert_eq!(text(source, right.span), "b * 2");
+        assert_eq!(text(source, statements[1].span), "return x;");
+    }
+
+    #[test]
+    fn syntax_errors_are_diagnostics() {
+        let source = "main -> sub() {\n    let x = ;\n}";
+        let mut diagnostics = Diagnostics::new();
+        Parser::parse(lexer::tokenize(source, 0, &mut diagnostics), &mut diagnostics);
+        let [error] = diagnostics.diagnostics() else {
+            panic!("Expected one error, got {:?}", diagnostics.diagnostics());
+        };
+        assert_eq!(error.code, codes::EXPECTED_EXPRESSION);
+        assert_eq!(error.message, "expected expression but found `;`");
+        assert_eq!(text(source, error.primary_span().unwrap()), ";");
+    }
+
+    fn parse(source: &str) -> (Stmt, Diagnostics) {
+        let mut diagnostics = Diagnostics::new();
+        let tokens = lexer::tokenize(source, 0, &mut diagnostics);
+        let ast = Parser::parse(tokens, &mut diagnostics);
+        (ast, diagnostics)
+    }
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        parse(source).1.diagnostics().iter().map(|d| d.code).collect()
+    }
+
+    /// Writes an expression fully parenthesized, so `a + b * c` becomes `(+ a (* b c))`
+    fn tree(expr: &Expr) -> String {
+        let op = |op: &Token| op.to_string().trim_matches('`').to_string();
+        match &expr.kind {
+            ExprKind::Int(v, _) => v.to_string(),
+            ExprKind::Symbol(name) => name.clone(),
+            ExprKind::Binary { left, op: o, right } => format!("({} {} {})", op(o), tree(left), tree(right)),
+            ExprKind::Prefix { op: o, right } => format!("({} {})", op(o), tree(right)),
+            ExprKind::Increment { target, decrement, postfix } => {
+                let o = if *decrement { "--" } else { "++" };
+                if *postfix { format!("({} {})", tree(target), o) } else { format!("({} {})", o, tree(target)) }
+            },
+            ExprKind::Cast { expr, target } => match &target.kind {
+                TypeKind::Symbol(name) => format!("(as {} {})", tree(expr), name),
+                other => panic!("No tree form for {:?}", other),
+            },
+            ExprKind::Ternary { condition, then, _else } => format!("(? {} {} {})", tree(condition), tree(then), tree(_else)),
+            ExprKind::Call { name, args } => format!("{}({})", name, args.iter().map(tree).collect::<Vec<_>>().join(", ")),
+            ExprKind::Access { lhs, rhs } => format!("{}.{}", tree(lhs), tree(rhs)),
+            ExprKind::Index { target, index } => format!("{}[{}]", tree(target), tree(index)),
+            ExprKind::Array(elements) => format!("[{}]", elements.iter().map(tree).collect::<Vec<_>>().join(", ")),
+            ExprKind::Assignment { assignee, right } => format!("(= {} {})", tree(assignee), tree(right)),
+            ExprKind::CompoundAssignment { assignee, op: o, right } => format!("({}= {} {})", op(o), tree(assignee), tree(right)),
+            other => panic!("No tree form for {:?}", other),
+        }
+    }
+
+    /// The tree of `source` parsed as a statement in a function body
+    fn expr(source: &str) -> String {
+        let (ast, diagnostics) = parse(&format!("main -> sub() {{ {}; }}", source));
+        assert!(!diagnostics.has_errors(), "`{}` failed to parse: {:?}", source, diagnostics.diagnostics());
+        let StmtKind::Block(body) = ast.kind else {
+            panic!("Expected the program to be a block");
+        };
+        let StmtKind::FunctionDeclaration { body: function, .. } = &body[0].kind else {
+            panic!("Expected a function declaration");
+        };
+        let StmtKind::Block(statements) = &function.kind else {
+            panic!("Expected the function body to be a block");
+        };
+        let [Stmt { kind: StmtKind::Expression(expr), .. }] = statements.as_slice() else {
+            panic!("Expected one expression statement, got {:?}", statements);
+        };
+        tree(expr)
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        assert_eq!(expr("a + b * c"), "(+ a (* b c))");
+        assert_eq!(expr("a - b - c"), "(- (- a b) c)");
+        assert_eq!(expr("a % b * c"), "(* (% a b) c)");
+        assert_eq!(expr("a << 1 + 2"), "(<< a (+ 1 2))");
+        assert_eq!(expr("a | b ^ c & d"), "(| a (^ b (& c d)))");
+    }
+
+    #[test]
+    fn exponent_is_right_associative() {
+        assert_eq!(expr("2 ^^ 3 ^^ 2"), "(^^ 2 (^^ 3 2))");
+        assert_eq!(expr("a * b ^^ c"), "(* a (^^ b c))");
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(expr("a || b && c"), "(|| a (&& b c))");
+        assert_eq!(expr("a && b || c"), "(|| (&& a b) c)");
+        assert_eq!(expr("a || b || c"), "(|| (|| a b) c)");
+        assert_eq!(expr("a == 1 && b < 2"), "(&& (== a 1) (< b 2))");
+    }
+
+    #[test]
+    fn prefix_operators() {
+        assert_eq!(expr("-f(x)"), "(- f(x))");
+        assert_eq!(expr("-a.b"), "(- a.b)");
+        assert_eq!(expr("-a * b"), "(* (- a) b)");
+        assert_eq!(expr("-x ^^ 2"), "(- (^^ x 2))");
+        assert_eq!(expr("2 ^^ -x"), "(^^ 2 (- x))");
+        assert_eq!(expr("!a && b"), "(&& (! a) b)");
+        assert_eq!(expr("~a | b"), "(| (~ a) b)");
+        assert_eq!(expr("-1"), "-1");
+    }
+
+    #[test]
+    fn casts_bind_below_exponents() {
+        assert_eq!(expr("-x as T"), "(as (- x) T)");
+        assert_eq!(expr("x ^^ 2 as T"), "(as (^^ x 2) T)");
+        assert_eq!(expr("a * b as T"), "(* a (as b T))");
+    }
+
+    #[test]
+    fn increments() {
+        assert_eq!(expr("i++ + 1"), "(+ (i ++) 1)");
+        assert_eq!(expr("--i"), "(-- i)");
+        assert_eq!(expr("-i++"), "(- (i ++))");
+        assert_eq!(expr("f(i++)"), "f((i ++))");
+    }
+
+    #[test]
+    fn ternary_is_below_logic() {
+        assert_eq!(expr("a || b ? x : y"), "(? (|| a b) x y)");
+        assert_eq!(expr("x = c ? 1 : 2"), "(= x (? c 1 2))");
+        assert_eq!(expr("a ? b : c ? d : e"), "(? a b (? c d e))");
+        assert_eq!(expr("a ? b ? c : d : e"), "(? a (? b c d) e)");
+    }
+
+    #[test]
+    fn compound_assignment_takes_the_whole_right_side() {
+        assert_eq!(expr("x *= y + 1"), "(*= x (+ y 1))");
+        assert_eq!(expr("p.x <<= 2"), "(<<= p.x 2)");
+        assert_eq!(expr("a = b += 1"), "(= a (+= b 1))");
+        assert_eq!(codes("main -> sub() { a + b += 1; }"), [codes::INVALID_ASSIGNMENT_TARGET]);
+    }
+
+    #[test]
+    fn reports_every_bad_statement() {
+        let source = "main -> sub() {\n    let = 5;\n    let y = ;\n    let z = 1;\n}\n";
+        assert_eq!(codes(source), [codes::UNEXPECTED_TOKEN, codes::EXPECTED_EXPRESSION]);
+    }
+
+    #[test]
+    fn recovers_at_the_next_declaration() {
+        let (ast, diagnostics) = parse("a -> sub() { let x = ; }\nb -> sub() {}\n) c -> sub() {}");
+        assert_eq!(diagnostics.error_count(), 2);
+        let StmtKind::Block(body) = ast.kind else {
+            panic!("Expected the program to be a block");
+        };
+        assert_eq!(body.len(), 3);
+    }
+
+    #[test]
+    fn only_names_can_be_called() {
+        assert_eq!(codes("main -> sub() { f()(); }"), [codes::INVALID_CALLEE]);
+        assert_eq!(codes("main -> sub() { (1 + 2)(); }"), [codes::INVALID_CALLEE]);
+        assert_eq!(codes("main -> sub() { a.b.c(1); f(2); }"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn stray_close_curly_is_skipped() {
+        let (ast, diagnostics) = parse("} a -> sub() {}");
+        assert_eq!(diagnostics.error_count(), 1);
+        let StmtKind::Block(body) = ast.kind else {
+            panic!("Expected the program to be a block");
+        };
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn indexing_binds_like_member_access() {
+        assert_eq!(expr("a[i].b[j + 1]"), "a[i].b[(+ j 1)]");
+        assert_eq!(expr("-a[0] * 2"), "(* (- a[0]) 2)");
+        assert_eq!(expr("a[i++] += [1, 2][0]"), "(+= a[(i ++)] [1, 2][0])");
+    }
+
+    #[test]
+    fn for_tells_ranges_from_arrays() {
+        let iterable = |source: &str| {
+            let (ast, diagnostics) = parse(&format!("main -> sub() {{ for x in {} {{ }} }}", source));
+            assert!(!diagnostics.has_errors(), "`{}` failed to parse: {:?}", source, diagnostics.diagnostics());
+            let StmtKind::Block(body) = ast.kind else {
+                panic!("Expected the program to be a block");
+            };
+            let StmtKind::FunctionDeclaration { body: function, .. } = &body[0].kind else {
+                panic!("Expected a function declaration");
+            };
+            let StmtKind::Block(statements) = &function.kind else {
+                panic!("Expected the function body to be a block");
+            };
+            let StmtKind::For { iterable, .. } = &statements[0].kind else {
+                panic!("Expected a for loop");
+            };
+            matches!(iterable.kind, ExprKind::Range { .. })
+        };
+        assert!(iterable("[0..n]"));
+        assert!(iterable("[a[0]..=f(1, 2)]"));
+        assert!(!iterable("[1, 2, 3]"));
+        assert!(!iterable("[[0, 1], [2, 3]]"));
+        assert!(!iterable("a"));
+    }
+}
@@ -1,26 +1,32 @@
 
 use crate::lexer::Token;
-use super::{lookup::LookupTable, Parser};
+use super::{lookup::LookupTable, ParseResult, Parser};
 
 #[derive(Clone, Copy)]
 #[repr(usize)]
 pub enum BindingPower {
     Default = 0,
-    Comma,
     Assignment,
-    Logical,
+    Ternary,
+    LogicalOr,
+    LogicalAnd,
     Relational,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Shift,
     Additive,
     Multiplicative,
     Unary,
+    Exponent,
     Call,
     Member,
     Primary,
 }
 
-pub type StmtHandler<S> = fn(parser: &mut Parser) -> S;
-pub type NudHandler<E> = fn(parser: &mut Parser) -> E;
-pub type LedHandler<E> = fn(parser: &mut Parser, left: E, bp: BindingPower) -> E;
+pub type StmtHandler<S> = fn(parser: &mut Parser) -> ParseResult<S>;
+pub type NudHandler<E> = fn(parser: &mut Parser) -> ParseResult<E>;
+pub type LedHandler<E> = fn(parser: &mut Parser, left: E, bp: BindingPower) -> ParseResult<E>;
 
 type StmtLookup<S> = LookupTable<StmtHandler<S>>;
 type NudLookup<E> = LookupTable<NudHandler<E>>;
@@ -0,0 +1,185 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Void,
+    Bool,
+
+    /// A unicode scalar value, stored as a `u32`
+    Char,
+    Int { bits: u32, signed: bool },
+    Float { bits: u32 },
+    Ref(Box<Ty>),
+    Struct(String),
+
+    /// `[len]elem`, stored inline like a struct
+    Array { len: usize, elem: Box<Ty> },
+
+    /// `[]elem`, a pointer to the first element along with how many there are.
+    /// The elements can only be written through a `[]mut elem`
+    Slice { elem: Box<Ty>, mutable: bool },
+
+    /// Given to expressions that already produced an error, it is compatible
+    /// with every other type so one mistake doesn't cascade into many
+    Error,
+}
+
+impl Ty {
+    pub fn from_name(name: &str) -> Option<Ty> {
+        Some(match name {
+            "void" => Ty::Void,
+            "bool" => Ty::Bool,
+            "char" => Ty::Char,
+            "i8" => Ty::Int { bits: 8, signed: true },
+            "i16" => Ty::Int { bits: 16, signed: true },
+            "i32" => Ty::Int { bits: 32, signed: true },
+            "i64" => Ty::Int { bits: 64, signed: true },
+            "u8" => Ty::Int { bits: 8, signed: false },
+            "u16" => Ty::Int { bits: 16, signed: false },
+            "u32" => Ty::Int { bits: 32, signed: false },
+            "u64" => Ty::Int { bits: 64, signed: false },
+            // Only 64 bit targets are supported, so the pointer sized integers are plain aliases
+            "isize" => Ty::Int { bits: 64, signed: true },
+            "usize" => Ty::Int { bits: 64, signed: false },
+            "f16" => Ty::Float { bits: 16 },
+            "f32" => Ty::Float { bits: 32 },
+            "f64" => Ty::Float { bits: 64 },
+            // Bytes of UTF-8 text, `as &i8` gives C the pointer but only literals are nul terminated
+            "string" => Ty::Slice { elem: Box::new(Ty::Int { bits: 8, signed: false }), mutable: false },
+            _ => return None,
+        })
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(self, Ty::Int { .. })
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Ty::Int { signed: true, .. })
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Ty::Float { .. })
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_int() || self.is_float()
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Ty::Error)
+    }
+
+    pub fn is_void(&self) -> bool {
+        matches!(self, Ty::Void)
+    }
+
+    /// Name of the struct this is or refers to, fields and methods are reachable through either
+    pub fn struct_name(&self) -> Option<&str> {
+        match self {
+            Ty::Struct(name) => Some(name),
+            Ty::Ref(inner) => match &**inner {
+                Ty::Struct(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Element type of an array, a slice or an array behind a reference, which is what indexing gives
+    pub fn element(&self) -> Option<&Ty> {
+        match self {
+            Ty::Array { elem, .. } | Ty::Slice { elem, .. } => Some(elem),
+            Ty::Ref(inner) => match &**inner {
+                Ty::Array { elem, .. } => Some(elem),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Length of an array known at compile time
+    pub fn array_len(&self) -> Option<usize> {
+        match self {
+            Ty::Array { len, .. } => Some(*len),
+            Ty::Ref(inner) => inner.array_len(),
+            _ => None,
+        }
+    }
+
+    /// Whether an integer literal can be represented by this type
+    pub fn holds_int(&self, value: i128) -> bool {
+        match self {
+            Ty::Int { bits, signed: true } => value >= -(1 << (bits - 1)) && value < 1 << (bits - 1),
+            Ty::Int { bits, signed: false } => value >= 0 && value < 1 << bits,
+            _ => false,
+        }
+    }
+
+    /// Whether a float literal is within the range of this type, precision lost to rounding is fine
+    pub fn holds_float(&self, value: f64) -> bool {
+        match self {
+            Ty::Float { bits: 16 } => value.abs() <= 65504.0,
+            Ty::Float { bits: 32 } => value.abs() <= f32::MAX as f64,
+            Ty::Float { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether every value of this type is exactly representable in `target`, which
+    /// is when a number is implicitly converted instead of being a type mismatch
+    pub fn widens_to(&self, target: &Ty) -> bool {
+        match (self, target) {
+            (Ty::Int { bits, signed }, Ty::Int { bits: to, signed: to_signed }) => {
+                bits < to && (signed == to_signed || *to_signed)
+            },
+            (Ty::Int { bits, signed }, Ty::Float { bits: to }) => {
+                let mantissa = match to {
+                    16 => 11,
+                    32 => 24,
+                    _ => 53,
+                };
+                let magnitude = if *signed { bits - 1 } else { *bits };
+                magnitude <= mantissa
+            },
+            (Ty::Float { bits }, Ty::Float { bits: to }) => bits < to,
+            _ => false,
+        }
+    }
+
+    /// Whether `as` can convert this type to `target`
+    pub fn casts_to(&self, target: &Ty) -> bool {
+        match (self, target) {
+            _ if self == target => true,
+            (from, to) if from.is_numeric() && to.is_numeric() => true,
+            (Ty::Bool, to) => to.is_int(),
+            // Any integer can become a `char`, values that aren't unicode scalars are on the caller
+            (Ty::Char, other) | (other, Ty::Char) => other.is_int(),
+            (Ty::Ref(_) | Ty::Slice { .. }, Ty::Ref(_)) => true,
+            (Ty::Ref(_), to) | (to, Ty::Ref(_)) => to.is_int(),
+            _ => false,
+        }
+    }
+
+    pub fn compatible(&self, other: &Ty) -> bool {
+        self == other || self.is_error() || other.is_error()
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Void => write!(f, "void"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Char => write!(f, "char"),
+            Ty::Int { bits, signed } => write!(f, "{}{}", if *signed { "i" } else { "u" }, bits),
+            Ty::Float { bits } => write!(f, "f{}", bits),
+            Ty::Ref(inner) => write!(f, "&{}", inner),
+            Ty::Struct(name) => write!(f, "{}", name),
+            Ty::Array { len, elem } => write!(f, "[{}]{}", len, elem),
+            Ty::Slice { elem, mutable: false } => write!(f, "[]{}", elem),
+            Ty::Slice { elem, mutable: true } => write!(f, "[]mut {}", elem),
+            Ty::Error => write!(f, "{{unknown}}"),
+        }
+    }
+}
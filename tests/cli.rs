@@ -0,0 +1,83 @@
+mod common;
+
+use common::*;
+use std::path::Path;
+
+#[test]
+fn reports_errors_with_source_and_exits_non_zero() {
+    let stderr = errors("main -> sub() {\n    let x = ;\n}\n");
+    assert!(stderr.starts_with("error[E0101]: expected expression but found `;`\n"), "{}", stderr);
+    assert!(stderr.contains("main.ig:2:13\n"), "{}", stderr);
+    assert!(stderr.contains("2 |     let x = ;\n"), "{}", stderr);
+    assert!(stderr.ends_with("error: aborting due to 1 previous error\n"), "{}", stderr);
+}
+
+#[test]
+fn json_errors_are_one_per_line() {
+    let scratch = Scratch::new("json");
+    let input = scratch.write("main.ig", "main -> sub() {\n    let x = $;\n}\n");
+    let out = ignis(&input, &scratch.dir.join("main"), &["--error-format=json"]);
+    assert_eq!(out.status.code(), Some(1));
+
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stderr);
+    assert!(lines[0].starts_with(r#"{"severity":"error","code":"E0001","message":"invalid token `$`","#), "{}", stderr);
+    assert!(lines[0].contains(r#""line":2,"column":13"#), "{}", stderr);
+    assert!(lines[1].starts_with(r#"{"severity":"error","code":"E0101","#), "{}", stderr);
+}
+
+#[test]
+fn usage_without_arguments() {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ignis")).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).starts_with("Usage: ignis <input> <output>"));
+}
+
+#[test]
+fn compiles_and_runs() {
+    let Some(run) = run(&program("    putchar(72);\n    putchar(105);")) else {
+        return;
+    };
+    assert_eq!((run.code, run.stdout.as_str()), (0, "Hi"));
+}
+
+#[test]
+fn diamond_include_is_linked_once() {
+    if !has_toolchain() {
+        return;
+    }
+    let scratch = Scratch::new("diamond");
+    let d = scratch.write("d.ig", "d -> sub() i32 { return 2; }\n");
+    let d = d.to_string_lossy();
+    let b = scratch.write("b.ig", &format!("include \"{}\"\nb -> sub() i32 {{ return d() + 1; }}\n", d));
+    let c = scratch.write("c.ig", &format!("include \"{}\"\nc -> sub() i32 {{ return d() + 3; }}\n", d));
+    let input = scratch.write("main.ig", &format!(
+        "include \"{}\"\ninclude \"{}\"\nputchar -> extern[putchar](c i32) i32;\n\nmain -> sub() {{\n    putchar(60 + b() + c());\n}}\n",
+        b.to_string_lossy(), c.to_string_lossy(),
+    ));
+    let program = scratch.dir.join("main");
+    let out = ignis(&input, &program, &[]);
+    assert!(out.status.success(), "failed to compile:\n{}", String::from_utf8_lossy(&out.stderr));
+
+    let out = std::process::Command::new(&program).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "D");
+}
+
+#[test]
+fn goal_example_compiles_and_runs() {
+    if !has_toolchain() {
+        return;
+    }
+    let scratch = Scratch::new("goal");
+    let input = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/goal.ig");
+    let program = scratch.dir.join("goal");
+    let out = ignis(&input, &program, &[]);
+    assert!(out.status.success(), "failed to compile:\n{}", String::from_utf8_lossy(&out.stderr));
+
+    let out = std::process::Command::new(&program).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "Distance:\n5\nMoved p1 to:\n5\n7\nNumbers:\n0\n1\n2\n3\n4\nRange with upper bound variable:\n0\n1\n2\n",
+    );
+}
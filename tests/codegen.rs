@@ -0,0 +1,759 @@
+mod common;
+
+use common::*;
+
+/// Runs `source` and gives back what it printed, `None` when the toolchain is missing
+fn output(source: &str) -> Option<String> {
+    let run = run(source)?;
+    assert_eq!(run.code, 0, "exited with {}, printed {:?}", run.code, run.stdout);
+    Some(run.stdout)
+}
+
+#[test]
+fn if_else_chains_merge() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+pick -> sub(n i32) {
+    if n == 0 {
+        putchar(97);
+    } else if n == 1 {
+        putchar(98);
+    } else {
+        putchar(99);
+    }
+    putchar(46);
+}
+
+main -> sub() {
+    pick(0);
+    pick(1);
+    pick(2);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "a.b.c.");
+}
+
+#[test]
+fn if_without_else_falls_through() {
+    let Some(out) = output(&program("    if 1 > 2 {\n        putchar(120);\n    }\n    if 2 > 1 {\n        putchar(121);\n    }\n    putchar(122);")) else { return };
+    assert_eq!(out, "yz");
+}
+
+#[test]
+fn branches_return_early() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+compare -> sub(n i32) i32 {
+    if n < 10 {
+        return 60;
+    } else if n == 10 {
+        return 61;
+    }
+    return 62;
+}
+
+early -> sub(n i32) {
+    if n == 0 {
+        return;
+    }
+    putchar(33);
+}
+
+main -> sub() {
+    putchar(compare(5));
+    putchar(compare(10));
+    putchar(compare(15));
+    early(0);
+    early(1);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "<=>!");
+}
+
+#[test]
+fn nested_if_inside_while() {
+    let Some(out) = output(&program("    mut i = 0;\n    while i < 6 {\n        if i < 3 {\n            if i == 1 {\n                putchar(66);\n            } else {\n                putchar(97);\n            }\n        } else {\n            putchar(122);\n        }\n        i = i + 1;\n    }")) else { return };
+    assert_eq!(out, "aBazzz");
+}
+
+#[test]
+fn while_checks_its_condition_first() {
+    let Some(out) = output(&program("    while 1 > 2 {\n        putchar(120);\n    }\n    putchar(121);")) else { return };
+    assert_eq!(out, "y");
+}
+
+#[test]
+fn locals_in_a_loop_body_reuse_their_stack_slot() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+P -> struct {
+    x i32,
+}
+
+main -> sub() {
+    mut i = 0;
+    while i < 10000000 {
+        let a = i + 1;
+        let p = P { x: a };
+        i = p.x;
+    }
+    putchar(79);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "O");
+}
+
+#[test]
+fn break_and_continue() {
+    let Some(out) = output(&program("    mut i = 0;\n    while true {\n        i = i + 1;\n        if i == 2 {\n            continue;\n        }\n        if i == 5 {\n            break;\n        }\n        putchar(48 + i);\n    }")) else { return };
+    assert_eq!(out, "134");
+}
+
+#[test]
+fn labeled_break_leaves_the_outer_loop() {
+    let source = "    mut i = 0;
+    outer: while i < 3 {
+        mut j = 0;
+        while j < 3 {
+            if j == 2 {
+                i = i + 1;
+                continue outer;
+            }
+            if i == 2 {
+                break outer;
+            }
+            putchar(97 + j);
+            j = j + 1;
+        }
+    }
+    putchar(46);";
+    let Some(out) = output(&program(source)) else { return };
+    assert_eq!(out, "abab.");
+}
+
+#[test]
+fn range_loops() {
+    let source = "    for i in [0..5] {\n        putchar(48 + i);\n    }\n    putchar(46);
+    for i in [0..=5 : 2] {\n        putchar(48 + i);\n    }\n    putchar(46);
+    let upper = 3;\n    for i in [0..upper] {\n        putchar(97 + i);\n    }\n    putchar(46);
+    for i in [5..2] {\n        putchar(120);\n    }\n    putchar(46);";
+    let Some(out) = output(&program(source)) else { return };
+    assert_eq!(out, "01234.024.abc..");
+}
+
+#[test]
+fn range_loops_stop_at_the_largest_value() {
+    let source = "    for i in [2147483640..=2147483647] {\n        putchar(97);\n    }
+    for i in [2147483600..2147483647 : 20] {\n        putchar(98);\n    }\n    putchar(46);";
+    let Some(out) = output(&program(source)) else { return };
+    assert_eq!(out, "aaaaaaaabbb.");
+}
+
+#[test]
+fn break_and_continue_in_for() {
+    let Some(out) = output(&program("    for i in [0..10] {\n        if i == 2 {\n            continue;\n        }\n        if i == 5 {\n            break;\n        }\n        putchar(48 + i);\n    }")) else { return };
+    assert_eq!(out, "0134");
+}
+
+#[test]
+fn methods_take_this_by_value_or_by_reference() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+Counter -> struct {
+    n i32,
+
+    get -> sub(this) i32 {
+        return this.n;
+    }
+
+    bump -> sub(&this, by i32) {
+        this.n = this.n + by;
+    }
+}
+
+main -> sub() {
+    mut c = Counter { n: 65 };
+    c.bump(1);
+    putchar(c.get());
+    c.bump(2);
+    putchar(c.n);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "BD");
+}
+
+#[test]
+fn methods_of_structs_from_nested_includes() {
+    if !has_toolchain() {
+        return;
+    }
+    let scratch = Scratch::new("nested-methods");
+    let c = scratch.write("c.ig", "P -> struct {\n    x i32,\n    get -> sub(this) i32 { return this.x; }\n}\n");
+    let b = scratch.write("b.ig", &format!("include \"{}\"\nmake -> sub() P {{ return P {{ x: 72 }}; }}\n", c.to_string_lossy()));
+    let input = scratch.write("main.ig", &format!(
+        "include \"{}\"\nputchar -> extern[putchar](c i32) i32;\n\nmain -> sub() {{\n    putchar(make().get());\n}}\n",
+        b.to_string_lossy(),
+    ));
+    let program = scratch.dir.join("main");
+    let out = ignis(&input, &program, &[]);
+    assert!(out.status.success(), "failed to compile:\n{}", String::from_utf8_lossy(&out.stderr));
+
+    let out = std::process::Command::new(&program).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "H");
+}
+
+#[test]
+fn signedness_picks_comparison_and_division() {
+    // `atoi` hands back the bits of a negative int, declaring it as returning
+    // `u32` is the only way to get an unsigned value before literals are typed
+    let source = "putchar -> extern[putchar](c i32) i32;
+atoi -> extern[atoi](s string) u32;
+
+yes -> sub(b bool) {
+    if b {
+        putchar(121);
+    } else {
+        putchar(110);
+    }
+}
+
+main -> sub() {
+    let minus_one = 0 - 1;
+    let minus_three = 0 - 3;
+    yes(minus_one < 0);
+    yes(((0 - 7) / 2) == minus_three);
+    let max = atoi(\"-1\");
+    let half = atoi(\"2147483644\");
+    yes(max > atoi(\"1\"));
+    yes((atoi(\"-7\") / atoi(\"2\")) == half);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "yyyy");
+}
+
+#[test]
+fn forward_and_mutually_recursive_calls() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+main -> sub() {
+    putchar(show(is_even(10)));
+    putchar(show(is_even(7)));
+    putchar(48 + fib(6));
+    let l = List { value: 72, next: Node { value: 105, parent: 0 as &Parent } };
+    putchar(l.value);
+    let n = l.next;
+    putchar(n.value);
+}
+
+show -> sub(b bool) i32 {
+    if b {
+        return 121;
+    }
+    return 110;
+}
+
+is_even -> sub(n i32) bool {
+    if n == 0 {
+        return true;
+    }
+    return is_odd(n - 1);
+}
+
+is_odd -> sub(n i32) bool {
+    if n == 0 {
+        return false;
+    }
+    return is_even(n - 1);
+}
+
+fib -> sub(n i32) i32 {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+List -> struct {
+    value i32,
+    next Node,
+}
+
+Node -> struct {
+    value i32,
+    parent &Parent,
+}
+
+Parent -> struct {
+    child &Node,
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "yn8Hi");
+}
+
+#[test]
+fn integers_widen_by_their_own_signedness() {
+    let Some(out) = output(&program("    let a: u8 = 200;\n    let b: i32 = a;\n    if b > 0 {\n        putchar(b - 100);\n    }\n    let c: i8 = 0 - 28;\n    let d: i64 = c;\n    if d < 0 {\n        putchar(121);\n    }")) else { return };
+    assert_eq!(out, "dy");
+}
+
+#[test]
+fn casts_convert_between_numbers() {
+    let Some(out) = output(&program("    let big = 321;\n    putchar(big as u8 as i32);\n    putchar(66.9 as i32);\n    let minus: i8 = 0 - 1;\n    if minus as u8 as i32 == 255 {\n        putchar(121);\n    }\n    putchar(true as i32 + 47);")) else { return };
+    assert_eq!(out, "ABy0");
+}
+
+#[test]
+fn remainder_bitwise_and_shifts() {
+    let source = program("    let a = 17;
+    putchar(48 + a % 5);
+    putchar(48 + (0 - 17) % 5);
+    putchar(a & 12 | 64);
+    putchar(1 << 6 ^ 1);
+    let minus = 0 - 128;
+    putchar((minus >> 1) + 100);
+    let big: u8 = 128;
+    putchar((big >> 1) as i32);");
+    let Some(out) = output(&source) else { return };
+    assert_eq!(out, "2.@A$@");
+}
+
+#[test]
+fn compound_assignments() {
+    let source = program("    mut b = 3;
+    b += 4;
+    b *= 10;
+    b -= 5;
+    b /= 5;
+    b %= 100;
+    b <<= 1;
+    b |= 64;
+    putchar(b);
+    b &= 95;
+    b ^= 1;
+    b >>= 1;
+    putchar(b);");
+    let Some(out) = output(&source) else { return };
+    assert_eq!(out, "Z-");
+}
+
+#[test]
+fn integer_powers() {
+    let source = program("    let two = 2;
+    putchar(two ^^ 6 + 1);
+    putchar(48 + two ^^ 0);
+    let minus_one = 0 - 1;
+    let minus_two = 0 - 2;
+    let minus_three = 0 - 3;
+    putchar(48 + two ^^ minus_one);
+    putchar(48 + 1 ^^ minus_three);
+    putchar(50 + minus_one ^^ minus_three);
+    putchar(50 + minus_one ^^ minus_two);
+    let zero = 0;
+    putchar(48 + zero ^^ minus_one);
+    let root = 2.0 ^^ 0.5 * 10.0;
+    putchar(root as i32 + 50);");
+    let Some(run) = run_with(&source, &["--release"]) else { return };
+    assert_eq!((run.code, run.stdout.as_str()), (0, "A101130@"));
+}
+
+#[test]
+fn negation_and_increments() {
+    let source = program("    let x = 3;
+    putchar(60 - x ^^ 2);
+    putchar(60 + -x ^^ 2);
+    putchar(60 + -x * 2);
+    mut i = 70;
+    putchar(i++);
+    putchar(i);
+    putchar(--i);
+    putchar(i--);
+    putchar(i);
+    mut f = 1.5;
+    f++;
+    putchar((f * 20.0) as i32);");
+    let Some(out) = output(&source) else { return };
+    assert_eq!(out, "336FGFFE2");
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+say -> sub(c i32, result bool) bool {
+    putchar(c);
+    return result;
+}
+
+main -> sub() {
+    if say(97, false) && say(98, true) {
+        putchar(33);
+    }
+    if say(99, true) || say(100, true) {
+        putchar(46);
+    }
+    if say(101, true) && say(102, false) || say(103, true) {
+        putchar(46);
+    }
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "ac.efg.");
+}
+
+#[test]
+fn ternaries_only_evaluate_the_taken_branch() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+say -> sub(c i32) i32 {
+    putchar(c);
+    return c;
+}
+
+main -> sub() {
+    let n = 3;
+    putchar(n > 2 ? say(97) : say(98));
+    putchar(n > 5 ? say(99) : n > 4 ? say(100) : say(101));
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "aaee");
+}
+
+#[test]
+fn inner_blocks_shadow_until_they_end() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+show -> sub(c i32) {
+    let c = c + 1;
+    putchar(c);
+}
+
+main -> sub() {
+    let x = 65;
+    if true {
+        let x = 66;
+        putchar(x);
+        while true {
+            let x = 67;
+            putchar(x);
+            break;
+        }
+        putchar(x);
+    }
+    putchar(x);
+    for x in [68..70] {
+        putchar(x);
+    }
+    show(x);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "BCBADEB");
+}
+
+#[test]
+fn access_chains_on_any_struct_value() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+In -> struct {
+    v i32,
+}
+
+Out -> struct {
+    inner In,
+}
+
+make -> sub(v i32) Out {
+    return Out { inner: In { v: v } };
+}
+
+first -> sub(o Out) i32 {
+    return o.inner.v;
+}
+
+main -> sub() {
+    putchar(make(65).inner.v);
+    putchar(first(make(66)));
+    mut o = make(67);
+    putchar(o.inner.v);
+    o.inner.v = 68;
+    putchar(o.inner.v);
+    mut i = 0;
+    while i < 10000000 {
+        i = make(i + 1).inner.v;
+    }
+    putchar(69);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "ABCDE");
+}
+
+#[test]
+fn struct_literals_on_the_stack_and_the_heap() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+P -> struct {
+    a i32,
+    b i32,
+}
+
+main -> sub() {
+    let p = P { b: 66, a: 65 };
+    putchar(p.a);
+    putchar(p.b);
+    let q = new P { a: 67, b: 68 };
+    q.b += 1;
+    putchar(q.a);
+    putchar(q.b);
+    delete q;
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "ABCE");
+}
+
+#[test]
+fn arrays_are_values() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+P -> struct {
+    n i32,
+    bump -> sub(&this) { this.n += 1; }
+}
+
+sum -> sub(a [3]i32) i32 {
+    mut c = a;
+    c[0] = 0;
+    mut total = 0;
+    for x in c { total += x; }
+    return total;
+}
+
+main -> sub() {
+    mut a = [65, 1, 2];
+    mut b = a;
+    b[0] = 90;
+    putchar(a[0]);
+    putchar(b[0]);
+    putchar(62 + sum(a));
+    putchar(a[0]);
+    mut ps = [P { n: 66 }, P { n: 67 }];
+    ps[1].bump();
+    for p in ps { putchar(p.n); }
+    let grid: [2][2]u8 = [[72, 73], [74, 75]];
+    for row in grid {
+        for c in row { putchar(c as i32); }
+    }
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "AZAABDHIJK");
+}
+
+#[test]
+fn compound_assignment_evaluates_its_target_once() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+main -> sub() {
+    mut a = [0, 0, 0];
+    mut i: usize = 0;
+    a[i++] += 5;
+    a[i++] += 6;
+    a[i] -= -7;
+    for x in a { putchar(48 + x); }
+    putchar(48 + i as i32);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "5672");
+}
+
+#[test]
+fn slices_view_arrays_in_place() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+sum -> sub(s []i32) i32 {
+    mut total = 0;
+    for x in s { total += x; }
+    return total;
+}
+
+clear -> sub(s []mut i32) {
+    for i in [0..s.len] { s[i] = 0; }
+}
+
+main -> sub() {
+    mut a = [1, 2, 3, 4];
+    putchar(60 + sum(a[..]));
+    putchar(60 + sum(a[2..]));
+    putchar(60 + sum(a[..2]));
+    let s = a[1..3];
+    putchar(48 + s.len as i32);
+    s[0] = 9;
+    putchar(48 + a[1]);
+    clear(s[1..]);
+    putchar(48 + a[2]);
+    a[..][3] += 1;
+    putchar(48 + a[3]);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "FC?2905");
+}
+
+#[test]
+fn out_of_bounds_stops_with_the_location() {
+    let source = "main -> sub() {
+    mut a = [1, 2, 3];
+    let s = a[1..];
+    mut i: usize = 0;
+    while i < 5 {
+        s[i] = 0;
+        i++;
+    }
+}
+";
+    let Some(stopped) = run(source) else { return };
+    assert_eq!(stopped.code, 101);
+    assert!(stopped.stdout.ends_with(":6:9: index out of bounds: the index is 2 but the length is 2\n"), "{}", stopped.stdout);
+
+    let source = "main -> sub() {
+    let a = [1, 2, 3];
+    mut end: usize = 4;
+    let s = a[1..end];
+}
+";
+    let Some(stopped) = run(source) else { return };
+    assert_eq!(stopped.code, 101);
+    assert!(stopped.stdout.ends_with("slice range 1..4 is out of bounds for length 3\n"), "{}", stopped.stdout);
+
+    let source = "main -> sub() {
+    mut step = 0;
+    for i in [0..10:step] { }
+}
+";
+    let Some(stopped) = run(source) else { return };
+    assert_eq!(stopped.code, 101);
+    assert!(stopped.stdout.ends_with("range step must be positive\n"), "{}", stopped.stdout);
+}
+
+#[test]
+fn checks_stop_the_program_unless_released() {
+    let cases = [
+        ("mut x: u8 = 250;\n    x += 10;\n    putchar(48 + x as i32);", "attempt to add with overflow", "4"),
+        ("mut x: i8 = 0 - 127;\n    x--;\n    putchar(48 + -x as i32 / 64);", "attempt to negate with overflow", ""),
+        ("let x: i32 = 0;\n    putchar(48 + 1 / x);", "attempt to divide by zero", ""),
+        ("let x: i32 = 0;\n    putchar(48 + 1 % x);", "attempt to calculate the remainder with a divisor of zero", ""),
+        ("let x: i32 = 40;\n    putchar(1 << x);", "attempt to shift left with overflow", ""),
+        ("mut x: u8 = 0;\n    x--;", "attempt to subtract with overflow", ""),
+        ("let base: i32 = 2;\n    let big = base ^^ 40;\n    putchar(33);", "attempt to raise to a power with overflow", "!"),
+        ("let base: i32 = 2;\n    let exp: i32 = 0 - 1;\n    putchar(48 + base ^^ exp);", "attempt to raise to a negative power", "0"),
+    ];
+    for (body, message, _) in cases {
+        let Some(checked) = run(&program(body)) else { return };
+        assert_eq!(checked.code, 101, "{}", body);
+        assert!(checked.stdout.ends_with(&format!("{}\n", message)), "{}: {}", body, checked.stdout);
+    }
+
+    // Without checks overflow wraps around
+    for (body, _, released) in cases.into_iter().filter(|(_, _, released)| !released.is_empty()) {
+        let Some(run) = run_with(&program(body), &["--release"]) else { return };
+        assert_eq!((run.code, run.stdout.as_str()), (0, released), "{}", body);
+    }
+}
+
+#[test]
+fn powers_that_fit_pass_the_checks() {
+    let source = program("    let base: i32 = 2;
+    putchar(base ^^ 16 == 65536 ? 89 : 78);
+    putchar(base ^^ 30 == 1073741824 ? 89 : 78);
+    putchar(base ^^ 0 == 1 ? 89 : 78);
+    let minus_two: i32 = 0 - 2;
+    putchar(minus_two ^^ 31 == 0 - 2147483647 - 1 ? 89 : 78);
+    let three: u8 = 3;
+    putchar(three ^^ 5 == 243 ? 89 : 78);");
+    let Some(out) = output(&source) else { return };
+    assert_eq!(out, "YYYYY");
+}
+
+#[test]
+fn strings_know_their_length() {
+    let source = "include \"std/io.ig\"
+include \"std/string.ig\"
+include \"std/c/stdio.ig\"
+
+main -> sub() {
+    let s = \"caf\\u{E9}\\t!\";
+    putchar(48 + s.len as i32);
+    putchar(10);
+    writes(s);
+    let full = str_concat(s[..3], \"e\");
+    writes(full);
+    putchar(str_eq(str_sub(full, 0, 3), \"caf\") ? 89 : 78);
+    putchar(str_compare(\"ab\", \"b\") < 0 ? 89 : 78);
+    putchar(48 + str_find(full, \"fe\") as i32);
+    str_free(full);
+    mut b: [2]u8 = [104, 105];
+    b[..][1] = 111;
+    writes(b[..]);
+    puts(\"hi\" as &i8);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "7\ncafé\t!\ncafe\nYY2ho\nhi\n");
+}
+
+#[test]
+fn chars_convert_to_and_from_integers() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+main -> sub() {
+    let c = 'a';
+    putchar(c as i32);
+    let b = 66 as char;
+    putchar(b as i32);
+    putchar(b < c ? 89 : 78);
+    let fire = '\\u{1F525}';
+    putchar(fire as u32 == 128293 ? 89 : 78);
+    let s = \"xyz\";
+    mut count = 0;
+    for byte in s {
+        if byte == 'y' || byte == 'z' { count++; }
+    }
+    putchar('0' + count);
+    putchar('\\n');
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "aBYY2\n");
+}
+
+#[test]
+fn number_literals_in_any_base() {
+    let source = "putchar -> extern[putchar](c i32) i32;
+
+main -> sub() {
+    putchar(0x41);
+    putchar(0b100_0010);
+    putchar(0o103);
+    let big = 1_000_000;
+    putchar(big / 10_000 == 100 ? 89 : 78);
+    let byte = 255u8;
+    putchar(byte as i32 == 255 ? 89 : 78);
+    let tiny = 2.5e-1f64;
+    putchar(tiny * 4.0 == 1.0 ? 89 : 78);
+    putchar(.5 + 3f32 == 3.5 ? 89 : 78);
+    putchar(10);
+}
+";
+    let Some(out) = output(source) else { return };
+    assert_eq!(out, "ABCYYYY\n");
+}
+
@@ -0,0 +1,89 @@
+#![allow(dead_code)]
+
+use std::{path::{Path, PathBuf}, process::{Command, Output}, sync::atomic::{AtomicUsize, Ordering}};
+
+/// A scratch directory for one test, removed when dropped
+pub struct Scratch {
+    pub dir: PathBuf,
+}
+
+impl Scratch {
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("ignis-{}-{}-{}", name, std::process::id(), id));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    pub fn write(&self, name: &str, source: &str) -> PathBuf {
+        let path = self.dir.join(name);
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Runs the compiler on `input` from the repository root, so `include "std/..."` resolves
+pub fn ignis(input: &Path, output: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ignis"))
+        .arg(input)
+        .arg(output)
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+/// Compiles `source` expecting it to fail, giving back what was reported
+pub fn errors(source: &str) -> String {
+    let scratch = Scratch::new("errors");
+    let input = scratch.write("main.ig", source);
+    let out = ignis(&input, &scratch.dir.join("main"), &[]);
+    assert!(!out.status.success(), "expected errors, got\n{}", String::from_utf8_lossy(&out.stdout));
+    String::from_utf8_lossy(&out.stderr).to_string()
+}
+
+/// Whether `llc` and `gcc` are around to turn the emitted IR into a program
+pub fn has_toolchain() -> bool {
+    ["llc", "gcc"].iter().all(|tool| Command::new(tool).arg("--version").output().is_ok_and(|o| o.status.success()))
+}
+
+pub struct Run {
+    pub code: i32,
+    pub stdout: String,
+}
+
+/// Builds `source` with `args` and runs it, or gives `None` when the toolchain isn't installed
+pub fn run_with(source: &str, args: &[&str]) -> Option<Run> {
+    if !has_toolchain() {
+        eprintln!("skipping, `llc` or `gcc` is not installed");
+        return None;
+    }
+
+    let scratch = Scratch::new("run");
+    let input = scratch.write("main.ig", source);
+    let program = scratch.dir.join("main");
+    let out = ignis(&input, &program, args);
+    assert!(out.status.success(), "failed to compile:\n{}", String::from_utf8_lossy(&out.stderr));
+
+    let out = Command::new(&program).output().unwrap();
+    Some(Run {
+        code: out.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
+    })
+}
+
+pub fn run(source: &str) -> Option<Run> {
+    run_with(source, &[])
+}
+
+/// Wraps `body` in a `main` that can print single characters with `putchar`
+pub fn program(body: &str) -> String {
+    format!("putchar -> extern[putchar](c i32) i32;\n\nmain -> sub() {{\n{}\n}}\n", body)
+}